@@ -0,0 +1,24 @@
+#![no_main]
+
+use cosmwasm_std::{from_json, to_json_vec};
+use libfuzzer_sys::fuzz_target;
+use social_tip_contract::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, SudoMsg};
+
+// Message parsing must never panic, and anything that parses must survive a
+// round trip unchanged.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(msg) = from_json::<ExecuteMsg>(data) {
+        let again: ExecuteMsg = from_json(to_json_vec(&msg).unwrap()).unwrap();
+        assert_eq!(msg, again);
+    }
+    if let Ok(msg) = from_json::<InstantiateMsg>(data) {
+        let again: InstantiateMsg = from_json(to_json_vec(&msg).unwrap()).unwrap();
+        assert_eq!(msg, again);
+    }
+    if let Ok(msg) = from_json::<SudoMsg>(data) {
+        let again: SudoMsg = from_json(to_json_vec(&msg).unwrap()).unwrap();
+        assert_eq!(msg, again);
+    }
+    let _ = from_json::<QueryMsg>(data);
+    let _ = from_json::<MigrateMsg>(data);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use social_tip_contract::helpers::validate_identifier;
+
+// identifiers come straight from users, so no input may panic
+fuzz_target!(|identifier: &str| {
+    let _ = validate_identifier(identifier.to_string());
+});
@@ -0,0 +1,107 @@
+// Offline checks for state snapshots taken with the `snapshot {}` and
+// `snapshot_page {}` queries.
+//
+//     snapshot assemble before/ > before.json
+//     snapshot check before.json after/
+//     snapshot diff before.json after.json
+//
+// A snapshot is a directory holding the `snapshot {}` result and every
+// `snapshot_page {}` result taken at the same height, one per file, or a
+// single file written by `assemble`. `assemble` stitches a directory into one
+// file, `check` reports every broken invariant in each snapshot, `diff` lists
+// the entries added, removed or changed between two. `check` and `diff` exit
+// with status 1 if they found anything.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use cosmwasm_std::from_json;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use social_tip_contract::snapshot::{assemble, check, diff, Snapshot, SnapshotHeader, SnapshotPage};
+
+// the `{"data": ...}` wrapper a node's smart query prints around the result
+#[derive(Deserialize)]
+struct QueryOutput<T> {
+    data: T,
+}
+
+fn parse<T: DeserializeOwned>(raw: &[u8]) -> Option<T> {
+    from_json::<T>(raw).or_else(|_| from_json::<QueryOutput<T>>(raw).map(|output| output.data)).ok()
+}
+
+fn load(path: &str) -> Result<Snapshot, String> {
+    if !Path::new(path).is_dir() {
+        let raw = std::fs::read(path).map_err(|err| format!("{}: {}", path, err))?;
+        return parse::<Snapshot>(&raw).ok_or_else(|| format!("{}: not a snapshot", path));
+    }
+    let mut files: Vec<_> = std::fs::read_dir(path)
+        .and_then(|dir| dir.map(|entry| entry.map(|e| e.path())).collect())
+        .map_err(|err| format!("{}: {}", path, err))?;
+    // sections are stitched in file name order, so name the pages in the
+    // order they were queried
+    files.sort();
+    let (mut header, mut pages) = (None, vec![]);
+    for file in files.iter().filter(|f| f.is_file()) {
+        let name = file.display();
+        let raw = std::fs::read(file).map_err(|err| format!("{}: {}", name, err))?;
+        if let Some(page) = parse::<SnapshotPage>(&raw) {
+            pages.push(page);
+        } else if let Some(found) = parse::<SnapshotHeader>(&raw) {
+            if header.replace(found).is_some() {
+                return Err(format!("{}: second snapshot header", name));
+            }
+        } else {
+            return Err(format!("{}: neither a snapshot header nor a page", name));
+        }
+    }
+    let header = header.ok_or_else(|| format!("{}: no snapshot header", path))?;
+    assemble(header, pages).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn run(args: &[String]) -> Result<bool, String> {
+    match args {
+        [command, path] if command == "assemble" => {
+            let snapshot = load(path)?;
+            let json = cosmwasm_std::to_json_string(&snapshot).map_err(|err| err.to_string())?;
+            println!("{}", json);
+            Ok(true)
+        }
+        [command, paths @ ..] if command == "check" && !paths.is_empty() => {
+            let mut clean = true;
+            for path in paths {
+                let snapshot = load(path)?;
+                let problems = check(&snapshot);
+                println!("== {} (height {}, {} problem(s))", path, snapshot.header.height, problems.len());
+                for problem in &problems {
+                    println!("{}", problem);
+                }
+                clean &= problems.is_empty();
+            }
+            Ok(clean)
+        }
+        [command, old, new] if command == "diff" => {
+            let (old, new) = (load(old)?, load(new)?);
+            println!("== height {} -> {}", old.header.height, new.header.height);
+            let changes = diff(&old, &new).map_err(|err| err.to_string())?;
+            for change in &changes {
+                println!("{}", change);
+            }
+            Ok(changes.is_empty())
+        }
+        _ => Err("usage: snapshot assemble <dir> | snapshot check <snapshot>... | snapshot diff <old> <new>".to_string()),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::from(2)
+        }
+    }
+}
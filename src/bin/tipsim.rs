@@ -0,0 +1,392 @@
+// Runs scenario files against the contract on a cw-multi-test chain and
+// prints what every step did: its events, everyone's balance and the storage
+// keys it touched.
+//
+//     cargo run --features tipsim --bin tipsim -- scenarios/fee_rounding.yaml
+//
+// Scenarios are YAML (or JSON). Accounts are referred to by name and get a
+// deterministic address. A step with `expect_error` must fail with a message
+// containing that text; any other failing step is a mismatch, and the run
+// exits with status 1 if there was one.
+
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+
+use cosmwasm_std::{Addr, Coin, Event, Uint128};
+use cw_multi_test::{App, AppResponse, ContractWrapper, Executor};
+use serde::Deserialize;
+
+use social_tip_contract::contract::{execute, instantiate, query, sudo};
+use social_tip_contract::helpers::secret_hash;
+use social_tip_contract::msg::{ConfigUpdate, ExecuteMsg, InstantiateMsg};
+use social_tip_contract::state::{RateLimits, ReferralPolicy, RetentionPolicy};
+
+const ADMIN: &str = "admin";
+const PLATFORM_WALLET: &str = "platform_wallet";
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    #[serde(default = "default_denom")]
+    denom: String,
+    // starting balance of each named account, in `denom`
+    #[serde(default)]
+    accounts: BTreeMap<String, u64>,
+    // applied by the admin right after instantiation
+    #[serde(default)]
+    config: ConfigUpdate,
+    steps: Vec<Step>,
+}
+
+fn default_denom() -> String {
+    "uxion".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+struct Step {
+    #[serde(flatten)]
+    action: Action,
+    expect_error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    Register {
+        user: String,
+        identifier: String,
+        #[serde(default)]
+        claim_pending: bool,
+        referrer: Option<String>,
+    },
+    Tip {
+        from: String,
+        identifier: String,
+        amount: u64,
+        memo: Option<String>,
+        // escrow the tip behind this claim secret
+        secret: Option<String>,
+        #[serde(default)]
+        from_balance: bool,
+    },
+    Claim {
+        user: String,
+        identifier: String,
+    },
+    Refund {
+        user: String,
+        identifier: String,
+    },
+    Deposit {
+        user: String,
+        amount: u64,
+    },
+    Withdraw {
+        user: String,
+        amount: Option<u64>,
+    },
+    Settle {
+        limit: Option<u32>,
+    },
+    WithdrawFees {},
+    UpdateConfig(ConfigUpdate),
+    Advance {
+        seconds: u64,
+    },
+    // any other message, sent as is
+    Execute {
+        sender: String,
+        msg: ExecuteMsg,
+        #[serde(default)]
+        funds: Vec<Coin>,
+    },
+}
+
+struct Sim {
+    app: App,
+    contract: Addr,
+    denom: String,
+    // every name seen so far, printed in balance lines
+    names: BTreeMap<String, Addr>,
+}
+
+impl Sim {
+    fn new(scenario: &Scenario) -> Result<Self, String> {
+        let mut app = App::default();
+        let mut names = BTreeMap::new();
+        for name in [ADMIN, PLATFORM_WALLET].into_iter().chain(scenario.accounts.keys().map(String::as_str)) {
+            names.insert(name.to_string(), app.api().addr_make(name));
+        }
+        app.init_modules(|router, _, storage| {
+            for (name, amount) in &scenario.accounts {
+                router
+                    .bank
+                    .init_balance(storage, &names[name], vec![Coin::new(u128::from(*amount), &scenario.denom)])
+                    .unwrap();
+            }
+        });
+        let code_id = app.store_code(Box::new(ContractWrapper::new(execute, instantiate, query).with_sudo(sudo)));
+        let contract = app
+            .instantiate_contract(code_id, names[ADMIN].clone(), &InstantiateMsg {
+                admin: None,
+                guardian: None,
+                token_denom: scenario.denom.clone(),
+                platform_wallet: names[PLATFORM_WALLET].to_string(),
+                fee_shares: vec![],
+                tip_retention: RetentionPolicy::default(),
+                rate_limits: RateLimits::default(),
+                claim_link_ttl: None,
+                max_relayer_fee: None,
+                referral: ReferralPolicy::default(),
+                micro_tip_threshold: None,
+            }, &[], "social-tip", None)
+            .map_err(|err| format!("instantiate failed: {}", err.root_cause()))?;
+        let mut sim = Sim { app, contract, denom: scenario.denom.clone(), names };
+        if scenario.config != ConfigUpdate::default() {
+            sim.execute(ADMIN, ExecuteMsg::UpdateConfig(scenario.config.clone()), vec![])
+                .map_err(|err| format!("config update failed: {}", err))?;
+        }
+        Ok(sim)
+    }
+
+    fn addr(&mut self, name: &str) -> Addr {
+        let api = self.app.api();
+        self.names.entry(name.to_string()).or_insert_with(|| api.addr_make(name)).clone()
+    }
+
+    fn coin(&self, amount: u64) -> Coin {
+        Coin::new(u128::from(amount), &self.denom)
+    }
+
+    fn execute(&mut self, sender: &str, msg: ExecuteMsg, funds: Vec<Coin>) -> Result<AppResponse, String> {
+        let sender = self.addr(sender);
+        let contract = self.contract.clone();
+        self.app
+            .execute_contract(sender, contract, &msg, &funds)
+            .map_err(|err| err.root_cause().to_string())
+    }
+
+    // run one action, returning a one-line description and its outcome
+    fn run(&mut self, action: Action) -> (String, Result<AppResponse, String>) {
+        match action {
+            Action::Register { user, identifier, claim_pending, referrer } => {
+                let msg = ExecuteMsg::Register { identifier: identifier.clone(), claim_pending, referrer };
+                (format!("register {} as {}", identifier, user), self.execute(&user, msg, vec![]))
+            }
+            Action::Tip { from, identifier, amount, memo, secret, from_balance } => {
+                let coin = self.coin(amount);
+                let funds = if from_balance { vec![] } else { vec![coin.clone()] };
+                let msg = ExecuteMsg::Transfer {
+                    identifier: identifier.clone(),
+                    amount: coin.clone(),
+                    memo,
+                    secret_hash: secret.as_deref().map(secret_hash),
+                    from_balance,
+                };
+                (format!("tip {} -> {} {}", from, identifier, coin), self.execute(&from, msg, funds))
+            }
+            Action::Claim { user, identifier } => {
+                let msg = ExecuteMsg::Claim { identifier: identifier.clone(), destination: None, denom: None, amount: None };
+                (format!("claim {} by {}", identifier, user), self.execute(&user, msg, vec![]))
+            }
+            Action::Refund { user, identifier } => {
+                let msg = ExecuteMsg::Refund { identifier: identifier.clone() };
+                (format!("refund {} by {}", identifier, user), self.execute(&user, msg, vec![]))
+            }
+            Action::Deposit { user, amount } => {
+                let coin = self.coin(amount);
+                (format!("deposit {} by {}", coin, user), self.execute(&user, ExecuteMsg::Deposit {}, vec![coin]))
+            }
+            Action::Withdraw { user, amount } => {
+                let msg = ExecuteMsg::Withdraw { denom: self.denom.clone(), amount: amount.map(Uint128::from) };
+                (format!("withdraw by {}", user), self.execute(&user, msg, vec![]))
+            }
+            Action::Settle { limit } => ("settle".to_string(), self.execute(ADMIN, ExecuteMsg::Settle { limit }, vec![])),
+            Action::WithdrawFees {} => {
+                ("withdraw fees".to_string(), self.execute(ADMIN, ExecuteMsg::WithdrawFees {}, vec![]))
+            }
+            Action::UpdateConfig(update) => {
+                ("update config".to_string(), self.execute(ADMIN, ExecuteMsg::UpdateConfig(update), vec![]))
+            }
+            Action::Advance { seconds } => {
+                self.app.update_block(|block| {
+                    block.time = block.time.plus_seconds(seconds);
+                    block.height += seconds / 5;
+                });
+                (format!("advance {}s", seconds), Ok(AppResponse::default()))
+            }
+            Action::Execute { sender, msg, funds } => {
+                (format!("execute {:?} by {}", msg, sender), self.execute(&sender, msg, funds))
+            }
+        }
+    }
+
+    // swap addresses for the names they were made from
+    fn named(&self, text: &str) -> String {
+        let mut text = text.replace(self.contract.as_str(), "contract");
+        for (name, addr) in &self.names {
+            text = text.replace(addr.as_str(), name);
+        }
+        text
+    }
+
+    fn print_events(&self, events: &[Event]) {
+        for event in events.iter().filter(|e| e.ty.starts_with("wasm-")) {
+            let attrs: Vec<String> = event
+                .attributes
+                .iter()
+                .filter(|a| a.key != "_contract_address")
+                .map(|a| format!("{}={}", a.key, self.named(&a.value)))
+                .collect();
+            println!("  event {}: {}", &event.ty["wasm-".len()..], attrs.join(" "));
+        }
+    }
+
+    fn print_balances(&self) {
+        let balance = |addr: &Addr| self.app.wrap().query_balance(addr, &self.denom).unwrap().amount;
+        let mut line: Vec<String> = self.names.iter().map(|(name, addr)| format!("{} {}", name, balance(addr))).collect();
+        line.push(format!("contract {}", balance(&self.contract)));
+        println!("  balances: {}", line.join(" | "));
+    }
+
+    fn storage(&self) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        self.app.dump_wasm_raw(&self.contract).into_iter().collect()
+    }
+}
+
+fn print_diff(sim: &Sim, before: &BTreeMap<Vec<u8>, Vec<u8>>, after: &BTreeMap<Vec<u8>, Vec<u8>>) {
+    for (key, value) in after {
+        match before.get(key) {
+            None => println!("  + {} = {}", sim.named(&render_key(key)), sim.named(&String::from_utf8_lossy(value))),
+            Some(old) if old != value => {
+                println!("  ~ {} = {}", sim.named(&render_key(key)), sim.named(&String::from_utf8_lossy(value)))
+            }
+            _ => {}
+        }
+    }
+    for key in before.keys().filter(|key| !after.contains_key(*key)) {
+        println!("  - {}", sim.named(&render_key(key)));
+    }
+}
+
+// Map keys are length-prefixed parts followed by the last part as is. Show
+// each part as text where printable, integer keys as numbers and anything
+// else as hex, so `received_totals/bob/uxion` reads as such.
+fn render_key(key: &[u8]) -> String {
+    let mut parts = vec![];
+    let mut rest = key;
+    while let Some((part, tail)) = length_prefixed(rest) {
+        parts.push(render_part(part));
+        rest = tail;
+    }
+    if !rest.is_empty() || parts.is_empty() {
+        parts.push(render_part(rest));
+    }
+    parts.join("/")
+}
+
+fn length_prefixed(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let len = u16::from_be_bytes([*bytes.first()?, *bytes.get(1)?]) as usize;
+    let part = bytes.get(2..2 + len)?;
+    (len > 0).then(|| (part, &bytes[2 + len..]))
+}
+
+fn render_part(bytes: &[u8]) -> String {
+    if !bytes.is_empty() && bytes.iter().all(|b| b.is_ascii_graphic()) {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    match bytes.len() {
+        8 => u64::from_be_bytes(bytes.try_into().unwrap()).to_string(),
+        16 => u128::from_be_bytes(bytes.try_into().unwrap()).to_string(),
+        _ => format!("0x{}", hex::encode(bytes)),
+    }
+}
+
+// Run a scenario, printing every step. Returns the number of mismatches.
+fn simulate(scenario: Scenario) -> Result<usize, String> {
+    let mut sim = Sim::new(&scenario)?;
+    let mut mismatches = 0;
+    sim.print_balances();
+    for (i, step) in scenario.steps.into_iter().enumerate() {
+        let before = sim.storage();
+        let (label, outcome) = sim.run(step.action);
+        println!("#{} {}", i + 1, label);
+        match (outcome, step.expect_error) {
+            (Ok(res), None) => {
+                println!("  ok");
+                sim.print_events(&res.events);
+            }
+            (Ok(_), Some(expected)) => {
+                println!("  MISMATCH: succeeded, expected error containing {:?}", expected);
+                mismatches += 1;
+            }
+            (Err(err), Some(expected)) if err.contains(&expected) => println!("  error (expected): {}", sim.named(&err)),
+            (Err(err), _) => {
+                println!("  MISMATCH: error: {}", sim.named(&err));
+                mismatches += 1;
+            }
+        }
+        sim.print_balances();
+        print_diff(&sim, &before, &sim.storage());
+    }
+    Ok(mismatches)
+}
+
+fn load(path: &str) -> Result<Scenario, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    serde_yaml::from_str(&text).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn main() -> ExitCode {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: tipsim <scenario.yaml>...");
+        return ExitCode::from(2);
+    }
+    let mut failed = false;
+    for path in paths {
+        println!("== {}", path);
+        match load(&path).and_then(simulate) {
+            Ok(0) => {}
+            Ok(mismatches) => {
+                println!("{} step(s) did not go as expected", mismatches);
+                failed = true;
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the scenarios shipped with the repo double as regression tests
+    #[test]
+    fn bundled_scenarios_run_as_expected() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/scenarios");
+        let mut paths: Vec<_> = std::fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+        for path in paths {
+            let scenario = load(path.to_str().unwrap()).unwrap();
+            assert_eq!(simulate(scenario), Ok(0), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn keys_are_split_into_their_parts() {
+        assert_eq!(render_key(b"config"), "config");
+        assert_eq!(render_key(b"\x00\x04tips\x00\x00\x00\x00\x00\x00\x00\x07"), "tips/7");
+        assert_eq!(render_key(b"\x00\x0freceived_totals\x00\x03bobuxion"), "received_totals/bob/uxion");
+        assert_eq!(render_key(b"\x00\x04pool\x01\x02"), "pool/0x0102");
+    }
+}
@@ -1,32 +1,235 @@
+use std::collections::BTreeMap;
+
 use cosmwasm_std::{
-    entry_point, to_json_binary, BankMsg, Binary, Coin, Deps, DepsMut,
-    Env, Event, MessageInfo, Response, StdError, StdResult,
+    entry_point, to_json_binary, Addr, BankMsg, Binary, Coin, Coins, Deps, DepsMut,
+    Env, MessageInfo, Order, Response, StdError, StdResult, Timestamp, Uint128,
+};
+use cw2::{set_contract_version, CONTRACT};
+use cw_storage_plus::{Bound, IndexedMap};
+
+use crate::error::ContractError;
+use crate::events::TipEvent;
+use crate::helpers::{
+    claim_commitment, claim_id, claim_reveal_hash, create_batch_response, create_response, secret_hash, validate_denom, validate_identifier,
+    validate_memo, validate_profile, validate_secret_hash,
+};
+use crate::ledger::{record_tip, set_tip_status, settle_batched};
+use crate::msg::{
+    ClaimPayload, ConfigUpdate, ExecuteMsg, FeeShareMsg, InstantiateMsg, MigrateMsg, SignatureScheme, SudoMsg, QueryMsg, BalanceResponse, AccountResponse, EscrowResponse, EscrowsResponse, TipResponse,
+    TipsResponse,
+    AllowanceInfo, AllowancesResponse, FeePoolResponse, InternalBalanceResponse, LeaderboardEntry, MicroTipsResponse, PendingMicroTips, PayoutPolicyMsg, PayoutPolicyResponse, ProfileResponse, ReferralEarning, ReferralEarningsResponse, ReferralResponse, LeaderboardResponse, PauseResponse, RelayNonceResponse, StatsResponse, BlockTarget, BlockedResponse,
+};
+use crate::fees::{
+    accrue, accrued, collect_fee, drain, platform_fee, validate_fee_shares, validate_referral_policy, TOTAL_BPS,
+};
+use crate::payout::{credit, debit, pay_out};
+use crate::rate_limit;
+use crate::relay::verify_claim;
+use crate::snapshot;
+use crate::state::{
+    escrows, received_totals, sent_totals, tips, Allowance, BlockEntry, Config, Escrow, FeeShare, PayoutPolicy, Profile, Referral, Operation, PauseState, RateLimits, ReferralPolicy, RetentionPolicy, TipStatus, TipTotal,
+    TotalIndexes, ACCOUNTS, BLOCKED_ADDRESSES, BLOCKED_IDENTIFIERS, CONFIG, DENOM_STATS, INBOUND_ESCROW_LOG, LEGACY_CONFIG, LEGACY_ESCROWS,
+    ALLOWANCES, BALANCES, CLAIM_COMMITS, MICRO_TIP_BUCKETS, PAUSE, PAYOUT_POLICIES, PROFILES, REFERRALS, REFERRAL_EARNINGS, RELAY_NONCES, SENDER_ESCROW_LOG,
 };
+use crate::stats::{record_received, record_sent};
 
-use crate::helpers::{create_response, validate_identifier};
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, BalanceResponse, AccountResponse, EscrowResponse};
-use crate::state::{Config, Escrow, ACCOUNTS, CONFIG, ESCROWS, TOKEN_DENOM};
+// pagination defaults for list queries
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+// micro tips batched per bucket. Settle updates the record of each one, so
+// this bounds its cost; once a bucket is full, tips to it are paid directly
+// until it is settled.
+const MAX_BATCH_TIPS: u32 = 30;
+
+// claim links stay valid for 30 days unless configured otherwise
+const DEFAULT_CLAIM_LINK_TTL: u64 = 30 * 24 * 60 * 60;
 
 // version info for migration info
-const _CONTRACT_NAME: &str = "crates.io:social-tip-contract";
-const _CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const CONTRACT_NAME: &str = "crates.io:social-tip-contract";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+// reported as the version migrated from when the first release is upgraded,
+// which never stored one
+const UNVERSIONED: &str = "unversioned";
+
+// who sudo calls are attributed to in events
+const GOVERNANCE: &str = "governance";
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    let admin = match msg.admin {
+        Some(admin) => deps.api.addr_validate(&admin)?,
+        None => info.sender,
+    };
+    let guardian = msg.guardian.map(|g| deps.api.addr_validate(&g)).transpose()?;
+    let platform_wallet = deps.api.addr_validate(&msg.platform_wallet)?;
+    let fee_shares = if msg.fee_shares.is_empty() {
+        default_fee_shares(&platform_wallet)
+    } else {
+        checked_fee_shares(deps.as_ref(), msg.fee_shares)?
+    };
     let config = Config {
+        admin: admin.clone(),
+        guardian,
         token_denom: msg.token_denom.clone(),
-        platform_wallet: msg.platform_wallet.clone(),
+        platform_wallet: platform_wallet.clone(),
+        fee_shares,
+        tip_retention: msg.tip_retention,
+        rate_limits: msg.rate_limits,
+        claim_link_ttl: msg.claim_link_ttl.unwrap_or(DEFAULT_CLAIM_LINK_TTL),
+        max_relayer_fee: msg.max_relayer_fee.unwrap_or_default(),
+        referral: msg.referral,
+        micro_tip_threshold: msg.micro_tip_threshold.unwrap_or_default(),
     };
+    validate_config(deps.as_ref(), &config)?;
     CONFIG.save(deps.storage, &config)?;
-    Ok(create_response("instantiate", vec![
-        ("token_denom", &msg.token_denom),
-        ("platform_wallet", &msg.platform_wallet),
-        ]))
+    PAUSE.save(deps.storage, &PauseState::default())?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(create_response(TipEvent::Instantiate {
+        admin: admin.to_string(),
+        token_denom: msg.token_denom,
+        platform_wallet: platform_wallet.to_string(),
+    }))
+}
+
+// Re-checks the stored config, so settings saved before a check existed are
+// caught here rather than at the next transfer. The first release stored no
+// contract version and only a denom and wallet; its config is upgraded here.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let mut moved = 0;
+    let from_version = match CONTRACT.may_load(deps.storage)? {
+        Some(stored) if stored.contract != CONTRACT_NAME => {
+            return Err(StdError::generic_err(format!("Cannot migrate from {}", stored.contract)));
+        }
+        Some(stored) => stored.version,
+        None => {
+            upgrade_legacy_config(deps.branch())?;
+            moved = upgrade_legacy_escrows(deps.branch(), &env)?;
+            UNVERSIONED.to_string()
+        }
+    };
+    let config = CONFIG.load(deps.storage)?;
+    validate_config(deps.as_ref(), &config)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(create_response(TipEvent::Migrate {
+        from_version,
+        to_version: CONTRACT_VERSION.to_string(),
+    })
+    .add_attribute("legacy_escrows", moved.to_string()))
+}
+
+// Fill in what the first release's config lacked with the instantiate
+// defaults. It had no admin, so the platform wallet becomes admin; governance
+// can hand that over with SudoMsg::UpdateConfig.
+fn upgrade_legacy_config(deps: DepsMut) -> StdResult<()> {
+    let legacy = LEGACY_CONFIG.load(deps.storage)?;
+    let platform_wallet = deps.api.addr_validate(&legacy.platform_wallet)?;
+    let config = Config {
+        admin: platform_wallet.clone(),
+        guardian: None,
+        token_denom: legacy.token_denom,
+        fee_shares: default_fee_shares(&platform_wallet),
+        platform_wallet,
+        tip_retention: RetentionPolicy::default(),
+        rate_limits: RateLimits::default(),
+        claim_link_ttl: DEFAULT_CLAIM_LINK_TTL,
+        max_relayer_fee: Uint128::zero(),
+        micro_tip_threshold: Uint128::zero(),
+        referral: ReferralPolicy::default(),
+    };
+    CONFIG.save(deps.storage, &config)?;
+    PAUSE.save(deps.storage, &PauseState::default())
+}
+
+// Move the first release's escrows, one per identifier, into escrows() with
+// a tip record each, so they are claimed and refunded like any other. Their
+// fee was paid out when they were sent, so the record has none and the held
+// amount as its gross. Returns how many were moved.
+fn upgrade_legacy_escrows(deps: DepsMut, env: &Env) -> StdResult<u64> {
+    let config = CONFIG.load(deps.storage)?;
+    let legacy = LEGACY_ESCROWS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut moved = 0;
+    for (identifier, escrow) in legacy {
+        LEGACY_ESCROWS.remove(deps.storage, identifier.clone());
+        // a zero tip left an empty escrow behind, there is nothing to move
+        if escrow.amount.amount.is_zero() {
+            continue;
+        }
+        let tip_id = record_tip(
+            deps.storage,
+            env,
+            &config.tip_retention,
+            &escrow.sender,
+            &identifier,
+            &escrow.amount,
+            Uint128::zero(),
+            escrow.amount.amount,
+            None,
+            TipStatus::Escrowed,
+        )?;
+        escrows().save(deps.storage, tip_id, &Escrow {
+            sender: escrow.sender,
+            identifier,
+            amount: escrow.amount,
+            tip_id,
+            expires_at: env.block.time.plus_seconds(config.claim_link_ttl),
+            secret_hash: None,
+        })?;
+        moved += 1;
+    }
+    Ok(moved)
+}
+
+// all fees to the platform wallet
+fn default_fee_shares(platform_wallet: &Addr) -> Vec<FeeShare> {
+    vec![FeeShare { address: platform_wallet.clone(), bps: TOTAL_BPS }]
+}
+
+// Checks shared by instantiate, UpdateConfig and migrate. Addresses are
+// validated again since older configs stored them unchecked.
+fn validate_config(deps: Deps, config: &Config) -> StdResult<()> {
+    validate_denom(&config.token_denom)?;
+    deps.api.addr_validate(config.admin.as_str())?;
+    if let Some(guardian) = &config.guardian {
+        deps.api.addr_validate(guardian.as_str())?;
+    }
+    deps.api.addr_validate(config.platform_wallet.as_str())?;
+    validate_fee_shares(deps.api, &config.fee_shares)?;
+    validate_referral_policy(&config.referral)?;
+    let limits = &config.rate_limits;
+    for min in &limits.min_tips {
+        validate_denom(&min.denom)?;
+    }
+    // with no window every earlier escrow has already expired, so a cap would
+    // never be reached
+    let capped = limits.max_escrows_per_sender.is_some() || limits.max_escrows_per_identifier.is_some();
+    if capped && limits.window == 0 {
+        return Err(StdError::generic_err("Escrow caps need a positive window"));
+    }
+    if config.claim_link_ttl == 0 {
+        return Err(StdError::generic_err("Claim link TTL must be positive"));
+    }
+    Ok(())
+}
+
+fn checked_fee_shares(deps: Deps, shares: Vec<FeeShareMsg>) -> StdResult<Vec<FeeShare>> {
+    shares
+        .into_iter()
+        .map(|share| {
+            Ok(FeeShare {
+                address: deps.api.addr_validate(&share.address)?,
+                bps: share.bps,
+            })
+        })
+        .collect()
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -35,117 +238,652 @@ pub fn execute(
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Register { identifier } => execute_register(deps, info, identifier),
-        ExecuteMsg::Transfer { identifier, amount } => execute_transfer(deps, env, info, identifier, &amount),
-        ExecuteMsg::Claim { identifier } => execute_claim(deps, info, identifier),    
+        ExecuteMsg::Register { identifier, claim_pending, referrer } => {
+            execute_register(deps, env, info, identifier, claim_pending, referrer)
+        }
+        ExecuteMsg::Transfer { identifier, amount, memo, secret_hash, from_balance } => {
+            execute_transfer(deps, env, info, identifier, &amount, memo, secret_hash, from_balance)
+        }
+        ExecuteMsg::Deposit {} => execute_deposit(deps, info),
+        ExecuteMsg::Settle { limit } => execute_settle(deps, env, limit),
+        ExecuteMsg::Claim { identifier, destination, denom, amount } => {
+            execute_claim(deps, info, identifier, destination, denom, amount)
+        }
+        ExecuteMsg::CommitClaim { escrow_id, commitment } => execute_commit_claim(deps, env, info, escrow_id, commitment),
+        ExecuteMsg::ClaimWithSecret { escrow_id, secret, recipient, register } => {
+            execute_claim_with_secret(deps, env, info, escrow_id, secret, recipient, register)
+        }
+        ExecuteMsg::RelayClaim { payload, scheme, pubkey, signature } => {
+            execute_relay_claim(deps, env, info, payload, scheme, pubkey, signature)
+        }
+        ExecuteMsg::Refund { identifier } => execute_refund(deps, info, identifier),
+        ExecuteMsg::WithdrawFees {} => execute_withdraw_fees(deps),
+        ExecuteMsg::SetProfile { identifier, profile } => execute_set_profile(deps, info, identifier, Some(profile)),
+        ExecuteMsg::RemoveProfile { identifier } => execute_set_profile(deps, info, identifier, None),
+        ExecuteMsg::SetPayoutPolicy { identifier, policy } => execute_set_payout_policy(deps, info, identifier, policy),
+        ExecuteMsg::Approve { spender, denom, amount, per_tip_cap, expires } => {
+            execute_approve(deps, env, info, spender, denom, amount, per_tip_cap, expires)
+        }
+        ExecuteMsg::Revoke { spender, denom } => execute_revoke(deps, info, spender, denom),
+        ExecuteMsg::TransferFrom { owner, identifier, amount, memo, secret_hash } => {
+            execute_transfer_from(deps, env, info, owner, identifier, &amount, memo, secret_hash)
+        }
+        ExecuteMsg::Withdraw { denom, amount } => execute_withdraw(deps, info, denom, amount),
+        ExecuteMsg::ClaimReferralRewards { identifier } => execute_claim_referral_rewards(deps, info, identifier),
+        ExecuteMsg::SetFeeShares { shares } => execute_set_fee_shares(deps, info, shares),
+        ExecuteMsg::UpdateConfig(update) => execute_update_config(deps, info, update),
+        ExecuteMsg::Pause { operations } => execute_set_pause(deps, info, operations, true),
+        ExecuteMsg::Unpause { operations } => execute_set_pause(deps, info, operations, false),
+        ExecuteMsg::Block { target, reason } => execute_block(deps, env, info, target, reason),
+        ExecuteMsg::Unblock { target } => execute_unblock(deps, info, target),
+        ExecuteMsg::ForceRefund { identifier } => execute_force_refund(deps, info, identifier),
+    }
+}
+
+// fail with ContractError::Paused if the operation is switched off
+fn assert_not_paused(deps: Deps, op: Operation) -> Result<(), ContractError> {
+    if PAUSE.may_load(deps.storage)?.unwrap_or_default().is_paused(op) {
+        return Err(ContractError::Paused { operation: op.to_string() });
+    }
+    Ok(())
+}
+
+// fail with ContractError::Blocked if the address is on the denylist
+fn assert_addr_not_blocked(deps: Deps, addr: &Addr) -> Result<(), ContractError> {
+    if BLOCKED_ADDRESSES.has(deps.storage, addr.clone()) {
+        return Err(ContractError::Blocked { target: addr.to_string() });
     }
+    Ok(())
+}
+
+// fail with ContractError::Blocked if the policy pays out to a blocked address,
+// which may have been blocked after the policy was set
+fn assert_policy_not_blocked(deps: Deps, policy: &PayoutPolicy) -> Result<(), ContractError> {
+    for address in policy.destinations() {
+        assert_addr_not_blocked(deps, address)?;
+    }
+    Ok(())
+}
+
+// fail with ContractError::Blocked if the identifier is on the denylist
+fn assert_identifier_not_blocked(deps: Deps, identifier: &str) -> Result<(), ContractError> {
+    if BLOCKED_IDENTIFIERS.has(deps.storage, identifier.to_string()) {
+        return Err(ContractError::Blocked { target: identifier.to_string() });
+    }
+    Ok(())
+}
+
+fn assert_admin(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    if CONFIG.load(deps.storage)?.admin != *sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
 }
 
 fn execute_register(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    identifier: String
-) -> StdResult<Response> {
+    identifier: String,
+    claim_pending: bool,
+    referrer: Option<String>,
+) -> Result<Response, ContractError> {
+    let response = register_identifier(deps.branch(), &env, identifier.clone(), &info.sender, referrer)?;
+    if !claim_pending {
+        return Ok(response);
+    }
+    // deliver tips that were waiting for this identifier in the same tx
+    let pending = claimable_escrows(deps.as_ref(), &identifier, None)?;
+    if pending.is_empty() {
+        return Ok(response);
+    }
+    assert_not_paused(deps.as_ref(), Operation::Claim)?;
+    let claim = release_escrows(deps, pending, &info.sender, None)?;
+    Ok(response
+        .add_attributes(claim.attributes)
+        .add_events(claim.events)
+        .add_submessages(claim.messages))
+}
+
+// map an identifier to an address, shared by register and claim-with-secret
+fn register_identifier(
+    deps: DepsMut,
+    env: &Env,
+    identifier: String,
+    address: &Addr,
+    referrer: Option<String>,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.as_ref(), Operation::Register)?;
     // Validate identifier (basic mail or username check)
     validate_identifier(identifier.clone())?;
+    assert_addr_not_blocked(deps.as_ref(), address)?;
+    assert_identifier_not_blocked(deps.as_ref(), &identifier)?;
 
     // check if identifier is already registered
     if ACCOUNTS.has(deps.storage, identifier.to_string()) {
-        return Err(StdError::generic_err("Identifier already registered"));
+        return Err(StdError::generic_err("Identifier already registered").into());
+    }
+    // the referrer must be someone else's registered identifier
+    if let Some(referrer) = &referrer {
+        match ACCOUNTS.may_load(deps.storage, referrer.clone())? {
+            Some(referrer_addr) if referrer_addr != *address => {}
+            Some(_) => return Err(StdError::generic_err("Cannot refer yourself").into()),
+            None => return Err(StdError::generic_err("Referrer is not registered").into()),
+        }
+        assert_identifier_not_blocked(deps.as_ref(), referrer)?;
     }
     // save mapping
-    ACCOUNTS.save(deps.storage, identifier.to_string(), &info.sender)?;
-    Ok(create_response(
-        "register",
-        vec![
-            ("identifier", &identifier),
-            ("address", info.sender.as_ref()),
-        ],
-    ))
+    ACCOUNTS.save(deps.storage, identifier.to_string(), address)?;
+    if let Some(referrer) = &referrer {
+        REFERRALS.save(deps.storage, identifier.clone(), &Referral {
+            referrer: referrer.clone(),
+            since: env.block.time,
+        })?;
+    }
+    Ok(create_response(TipEvent::Register {
+        identifier,
+        address: address.to_string(),
+        referrer,
+    }))
+}
+
+// set or, with None, remove the profile of an identifier the sender owns
+fn execute_set_profile(
+    deps: DepsMut,
+    info: MessageInfo,
+    identifier: String,
+    profile: Option<Profile>,
+) -> Result<Response, ContractError> {
+    match ACCOUNTS.may_load(deps.storage, identifier.clone())? {
+        Some(owner) if owner == info.sender => {}
+        _ => return Err(ContractError::Unauthorized {}),
+    }
+    assert_addr_not_blocked(deps.as_ref(), &info.sender)?;
+    assert_identifier_not_blocked(deps.as_ref(), &identifier)?;
+    let removed = profile.is_none();
+    match profile {
+        Some(profile) => {
+            validate_profile(&profile)?;
+            PROFILES.save(deps.storage, identifier.clone(), &profile)?;
+        }
+        None => PROFILES.remove(deps.storage, identifier.clone()),
+    }
+    Ok(create_response(TipEvent::Profile {
+        identifier,
+        address: info.sender.to_string(),
+        removed,
+    }))
+}
+
+fn execute_set_payout_policy(
+    deps: DepsMut,
+    info: MessageInfo,
+    identifier: String,
+    policy: PayoutPolicyMsg,
+) -> Result<Response, ContractError> {
+    match ACCOUNTS.may_load(deps.storage, identifier.clone())? {
+        Some(owner) if owner == info.sender => {}
+        _ => return Err(ContractError::Unauthorized {}),
+    }
+    assert_addr_not_blocked(deps.as_ref(), &info.sender)?;
+    assert_identifier_not_blocked(deps.as_ref(), &identifier)?;
+    let policy = match policy {
+        PayoutPolicyMsg::Direct => PayoutPolicy::Direct,
+        PayoutPolicyMsg::Forward { address } => PayoutPolicy::Forward {
+            address: deps.api.addr_validate(&address)?,
+        },
+        PayoutPolicyMsg::Split { savings, bps } => {
+            if bps == 0 || bps >= TOTAL_BPS {
+                return Err(StdError::generic_err(format!("Savings share must be between 1 and {} bps", TOTAL_BPS - 1)).into());
+            }
+            PayoutPolicy::Split { savings: deps.api.addr_validate(&savings)?, bps }
+        }
+        PayoutPolicyMsg::Hold => PayoutPolicy::Hold,
+    };
+    match &policy {
+        PayoutPolicy::Forward { address } => assert_addr_not_blocked(deps.as_ref(), address)?,
+        PayoutPolicy::Split { savings, .. } => assert_addr_not_blocked(deps.as_ref(), savings)?,
+        _ => {}
+    }
+    if policy == PayoutPolicy::Direct {
+        PAYOUT_POLICIES.remove(deps.storage, identifier.clone());
+    } else {
+        PAYOUT_POLICIES.save(deps.storage, identifier.clone(), &policy)?;
+    }
+    Ok(create_response(TipEvent::PayoutPolicy {
+        identifier,
+        policy: policy.kind().to_string(),
+        owner: info.sender.to_string(),
+    }))
+}
+
+// Pay out up to `limit` micro-tip buckets. Buckets of blocked identifiers or
+// recipients are left pending. The fee is taken once per bucket, so tips too
+// small to pay a fee alone still contribute to it.
+fn execute_settle(deps: DepsMut, env: Env, limit: Option<u32>) -> Result<Response, ContractError> {
+    assert_not_paused(deps.as_ref(), Operation::Claim)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let config = CONFIG.load(deps.storage)?;
+
+    let mut events = vec![];
+    let mut msgs = vec![];
+    // buckets are read one at a time, paid ones are removed as we go
+    let mut last: Option<(String, String)> = None;
+    while events.len() < limit {
+        let next = MICRO_TIP_BUCKETS
+            .range(deps.storage, last.take().map(Bound::exclusive), None, Order::Ascending)
+            .next()
+            .transpose()?;
+        let Some(((identifier, denom), bucket)) = next else {
+            break;
+        };
+        last = Some((identifier.clone(), denom.clone()));
+        let recipient = ACCOUNTS.load(deps.storage, identifier.clone())?;
+        let policy = PAYOUT_POLICIES.may_load(deps.storage, identifier.clone())?.unwrap_or_default();
+        if BLOCKED_IDENTIFIERS.has(deps.storage, identifier.clone())
+            || BLOCKED_ADDRESSES.has(deps.storage, recipient.clone())
+            || policy.destinations().into_iter().any(|a| BLOCKED_ADDRESSES.has(deps.storage, a.clone()))
+        {
+            continue;
+        }
+        MICRO_TIP_BUCKETS.remove(deps.storage, (identifier.clone(), denom.clone()));
+        let fee = platform_fee(bucket.gross);
+        let net = Coin::new(bucket.gross - fee, &denom);
+        settle_batched(deps.storage, &bucket.tip_ids, bucket.gross, fee)?;
+        collect_fee(deps.storage, env.block.time, &config.referral, &identifier, &Coin::new(fee, &denom))?;
+        record_received(deps.storage, &identifier, &net, u64::from(bucket.tips))?;
+        msgs.extend(pay_out(deps.storage, &recipient, &policy, &net)?);
+        events.push(TipEvent::Settle {
+            identifier,
+            recipient: recipient.to_string(),
+            denom,
+            tips: bucket.tips,
+            gross_amount: bucket.gross,
+            fee,
+            net_amount: net.amount,
+            payout: policy.kind().to_string(),
+        });
+    }
+    if events.is_empty() {
+        return Err(StdError::generic_err("No micro tips to settle").into());
+    }
+    Ok(create_batch_response("settle", events).add_messages(msgs))
+}
+
+// credit the attached funds to the sender's internal balance
+fn execute_deposit(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    assert_addr_not_blocked(deps.as_ref(), &info.sender)?;
+    if info.funds.is_empty() {
+        return Err(StdError::generic_err("No funds attached").into());
+    }
+    let config = CONFIG.load(deps.storage)?;
+    if info.funds.iter().any(|coin| coin.denom != config.token_denom) {
+        return Err(StdError::generic_err("Invalid token denomination").into());
+    }
+    let mut events = vec![];
+    for coin in &info.funds {
+        credit(deps.storage, &info.sender, coin)?;
+        events.push(TipEvent::Deposit {
+            address: info.sender.to_string(),
+            denom: coin.denom.clone(),
+            amount: coin.amount,
+        });
+    }
+    Ok(create_batch_response("deposit", events))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn execute_approve(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: String,
+    denom: String,
+    amount: Uint128,
+    per_tip_cap: Option<Uint128>,
+    expires: Option<Timestamp>,
+) -> Result<Response, ContractError> {
+    let spender = deps.api.addr_validate(&spender)?;
+    if spender == info.sender {
+        return Err(StdError::generic_err("Cannot approve yourself").into());
+    }
+    validate_denom(&denom)?;
+    if amount.is_zero() {
+        return Err(StdError::generic_err("Allowance must be positive").into());
+    }
+    if per_tip_cap.is_some_and(|cap| cap.is_zero()) {
+        return Err(StdError::generic_err("Per-tip cap must be positive").into());
+    }
+    if expires.is_some_and(|at| at <= env.block.time) {
+        return Err(StdError::generic_err("Allowance already expired").into());
+    }
+    let allowance = Allowance { remaining: amount, per_tip_cap, expires };
+    ALLOWANCES.save(deps.storage, (info.sender.clone(), spender.clone(), denom.clone()), &allowance)?;
+    Ok(create_response(TipEvent::Approval {
+        owner: info.sender.to_string(),
+        spender: spender.to_string(),
+        denom,
+        amount,
+        per_tip_cap,
+        expires,
+        revoked: false,
+    }))
+}
+
+fn execute_revoke(deps: DepsMut, info: MessageInfo, spender: String, denom: String) -> Result<Response, ContractError> {
+    let spender = deps.api.addr_validate(&spender)?;
+    let key = (info.sender.clone(), spender.clone(), denom.clone());
+    if !ALLOWANCES.has(deps.storage, key.clone()) {
+        return Err(StdError::generic_err("No allowance to revoke").into());
+    }
+    ALLOWANCES.remove(deps.storage, key);
+    Ok(create_response(TipEvent::Approval {
+        owner: info.sender.to_string(),
+        spender: spender.to_string(),
+        denom,
+        amount: Uint128::zero(),
+        per_tip_cap: None,
+        expires: None,
+        revoked: true,
+    }))
+}
+
+// Tip out of the owner's internal balance as if they had sent a Transfer with
+// `from_balance`. The allowance is only spent once the transfer went through.
+#[allow(clippy::too_many_arguments)]
+fn execute_transfer_from(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: String,
+    identifier: String,
+    amount: &Coin,
+    memo: Option<String>,
+    secret_hash: Option<String>,
+) -> Result<Response, ContractError> {
+    assert_addr_not_blocked(deps.as_ref(), &info.sender)?;
+    let owner = deps.api.addr_validate(&owner)?;
+    let key = (owner.clone(), info.sender.clone(), amount.denom.clone());
+    let mut allowance = ALLOWANCES
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::Unauthorized {})?;
+    if allowance.expires.is_some_and(|at| at <= env.block.time) {
+        return Err(StdError::generic_err("Allowance expired").into());
+    }
+    if allowance.per_tip_cap.is_some_and(|cap| amount.amount > cap) {
+        return Err(StdError::generic_err("Tip exceeds the per-tip cap").into());
+    }
+    allowance.remaining = allowance
+        .remaining
+        .checked_sub(amount.amount)
+        .map_err(|_| StdError::generic_err("Allowance exceeded"))?;
+
+    let spender = info.sender.clone();
+    let on_behalf = MessageInfo { sender: owner.clone(), funds: info.funds };
+    let res = execute_transfer(deps.branch(), env, on_behalf, identifier, amount, memo, secret_hash, true)?;
+    if allowance.remaining.is_zero() {
+        ALLOWANCES.remove(deps.storage, key);
+    } else {
+        ALLOWANCES.save(deps.storage, key, &allowance)?;
+    }
+    Ok(res.add_event(TipEvent::TransferFrom {
+        owner: owner.to_string(),
+        spender: spender.to_string(),
+        denom: amount.denom.clone(),
+        amount: amount.amount,
+        remaining: allowance.remaining,
+    }))
 }
 
+// pay out (part of) the sender's internal balance in one denom
+fn execute_withdraw(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    amount: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    assert_addr_not_blocked(deps.as_ref(), &info.sender)?;
+    let balance = BALANCES.may_load(deps.storage, (info.sender.clone(), denom.clone()))?.unwrap_or_default();
+    let amount = amount.unwrap_or(balance);
+    if amount.is_zero() {
+        return Err(StdError::generic_err("Nothing to withdraw").into());
+    }
+    if amount > balance {
+        return Err(StdError::generic_err(format!("Insufficient balance: {}{}", balance, denom)).into());
+    }
+    let coin = Coin::new(amount, &denom);
+    debit(deps.storage, &info.sender, &coin)?;
+    Ok(create_response(TipEvent::Withdraw {
+        address: info.sender.to_string(),
+        denom,
+        amount,
+    })
+    .add_message(BankMsg::Send {
+        to_address: info.sender.to_string(),
+        amount: vec![coin],
+    }))
+}
+
+#[allow(clippy::too_many_arguments)]
 fn execute_transfer(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     identifier: String,
     amount: &Coin,
-) -> StdResult<Response> {
+    memo: Option<String>,
+    secret_hash: Option<String>,
+    from_balance: bool,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.as_ref(), Operation::Transfer)?;
     // Validate identifier and token denomination
     validate_identifier(identifier.clone())?;
+    if let Some(memo) = &memo {
+        validate_memo(memo)?;
+    }
+    let secret_hash = secret_hash.map(|h| h.to_lowercase());
+    if let Some(hash) = &secret_hash {
+        validate_secret_hash(hash)?;
+    }
+    assert_addr_not_blocked(deps.as_ref(), &info.sender)?;
+    assert_identifier_not_blocked(deps.as_ref(), &identifier)?;
 
     let config = CONFIG.load(deps.storage)?;
 
     if amount.denom != config.token_denom {
-        return Err(StdError::generic_err("Invalid token denomination"));
+        return Err(StdError::generic_err("Invalid token denomination").into());
+    }
+    // dust tips are rejected outright
+    if let Some(min) = config.rate_limits.min_tips.iter().find(|c| c.denom == amount.denom) {
+        if amount.amount < min.amount {
+            return Err(ContractError::TipTooSmall { min: min.to_string() });
+        }
     }
 
     // implement platfrom fee for transfers
-    let platfrom_fee = amount.amount.multiply_ratio(1u128, 100u128);  //1%
+    let platfrom_fee = platform_fee(amount.amount);
     let recipient_amount = {
         match amount.amount.checked_sub(platfrom_fee) {
             Ok(val) => val,
-            Err(_) => return Err(StdError::generic_err("Amount too small to cover fees")),
+            Err(_) => return Err(StdError::generic_err("Amount too small to cover fees").into()),
         }
     };
 
-    let fee_msg = BankMsg::Send {
-        to_address: config.platform_wallet.clone(),
-        amount: vec![Coin {
-            denom: amount.denom.clone(),
-            amount: platfrom_fee,
-        }],
-    };
+    if from_balance {
+        if !info.funds.is_empty() {
+            return Err(StdError::generic_err("Do not attach funds when tipping from balance").into());
+        }
+        // fails before anything is written if the balance is too low
+        debit(deps.storage, &info.sender, amount)
+            .map_err(|_| StdError::generic_err("Insufficient balance"))?;
+    } else if info.funds != [amount.clone()] {
+        // the tip is paid out of what was attached, so it must be exactly that
+        return Err(StdError::generic_err(format!("Attach exactly {} to send this tip", amount)).into());
+    }
+    record_sent(deps.storage, info.sender.as_str(), amount)?;
+    let fee = Coin::new(platfrom_fee, &amount.denom);
+
+    // small tips wait in the recipient's bucket unless it is full
+    let key = (identifier.clone(), amount.denom.clone());
+    let batch = amount.amount < config.micro_tip_threshold
+        && MICRO_TIP_BUCKETS.may_load(deps.storage, key.clone())?.is_none_or(|b| b.tips < MAX_BATCH_TIPS);
 
     // Check if recipient is registered
     match ACCOUNTS.may_load(deps.storage, identifier.to_string())? {
+        Some(recipient_addr) if batch => {
+            assert_addr_not_blocked(deps.as_ref(), &recipient_addr)?;
+            let policy = PAYOUT_POLICIES.may_load(deps.storage, identifier.clone())?.unwrap_or_default();
+            assert_policy_not_blocked(deps.as_ref(), &policy)?;
+            // no per-tip fee, Settle charges it on the bucket total
+            let tip_id = record_tip(
+                deps.storage,
+                &env,
+                &config.tip_retention,
+                &info.sender,
+                &identifier,
+                amount,
+                Uint128::zero(),
+                amount.amount,
+                memo,
+                TipStatus::Batched,
+            )?;
+            let mut bucket = MICRO_TIP_BUCKETS.may_load(deps.storage, key.clone())?.unwrap_or_default();
+            bucket.gross += amount.amount;
+            bucket.tips += 1;
+            bucket.tip_ids.push(tip_id);
+            MICRO_TIP_BUCKETS.save(deps.storage, key, &bucket)?;
+            Ok(create_response(TipEvent::MicroTip {
+                tip_id,
+                sender: info.sender.to_string(),
+                identifier,
+                denom: amount.denom.clone(),
+                amount: amount.amount,
+                pending_amount: bucket.gross,
+            }))
+        }
         Some(recipient_addr) => {
-            // Transfer tokens directly
-            let transfer_msg = BankMsg::Send { 
-                to_address: recipient_addr.to_string(),
-                amount: vec![Coin {
-                    denom: amount.denom.clone(),
-                    amount: recipient_amount,
-                }], 
+            assert_addr_not_blocked(deps.as_ref(), &recipient_addr)?;
+            let policy = PAYOUT_POLICIES.may_load(deps.storage, identifier.clone())?.unwrap_or_default();
+            if !from_balance {
+                assert_policy_not_blocked(deps.as_ref(), &policy)?;
+            }
+            // fees wait in the pool until WithdrawFees pays them out, less the
+            // referrer's cut if the recipient was referred
+            let referral = collect_fee(deps.storage, env.block.time, &config.referral, &identifier, &fee)?;
+            let tip_id = record_tip(
+                deps.storage,
+                &env,
+                &config.tip_retention,
+                &info.sender,
+                &identifier,
+                amount,
+                platfrom_fee,
+                recipient_amount,
+                memo,
+                TipStatus::Sent,
+            )?;
+            let net = Coin {
+                denom: amount.denom.clone(),
+                amount: recipient_amount,
+            };
+            record_received(deps.storage, &identifier, &net, 1)?;
+            // Transfer tokens the way the recipient asked for, or keep them
+            // on the internal ledger when the tip came from there
+            let (payout, payout_msgs) = if from_balance {
+                credit(deps.storage, &recipient_addr, &net)?;
+                ("internal", vec![])
+            } else {
+                (policy.kind(), pay_out(deps.storage, &recipient_addr, &policy, &net)?)
             };
-            Ok(create_response(
-                "transfer",
-                vec![
-                    ("sender", info.sender.as_ref()),
-                    ("recipient", &identifier),
-                    ("amount", &amount.amount.to_string()),
-                    ("fee", &platfrom_fee.to_string()),
-                ],
-            ).add_messages(vec![transfer_msg, fee_msg]))
+            let mut response = create_response(TipEvent::Transfer {
+                tip_id,
+                sender: info.sender.to_string(),
+                identifier: identifier.clone(),
+                recipient: recipient_addr.to_string(),
+                denom: amount.denom.clone(),
+                gross_amount: amount.amount,
+                fee: platfrom_fee,
+                net_amount: recipient_amount,
+                payout: payout.to_string(),
+            }).add_messages(payout_msgs);
+            if let Some((referrer, reward)) = referral {
+                response = response.add_event(TipEvent::ReferralReward {
+                    tip_id,
+                    referrer,
+                    referred: identifier,
+                    denom: amount.denom.clone(),
+                    amount: reward,
+                });
+            }
+            Ok(response)
         }
         None => {
+            let limits = &config.rate_limits;
+            if let Some(max) = limits.max_escrows_per_sender {
+                rate_limit::hit(
+                    deps.storage,
+                    &SENDER_ESCROW_LOG,
+                    info.sender.clone(),
+                    env.block.time,
+                    limits.window,
+                    max,
+                    ContractError::SenderRateLimited {},
+                )?;
+            }
+            if let Some(max) = limits.max_escrows_per_identifier {
+                rate_limit::hit(
+                    deps.storage,
+                    &INBOUND_ESCROW_LOG,
+                    identifier.clone(),
+                    env.block.time,
+                    limits.window,
+                    max,
+                    ContractError::InboundCapReached { identifier: identifier.clone() },
+                )?;
+            }
+            // unregistered identifiers have no referrer, the whole fee is pooled
+            accrue(deps.storage, &fee)?;
+            let tip_id = record_tip(
+                deps.storage,
+                &env,
+                &config.tip_retention,
+                &info.sender,
+                &identifier,
+                amount,
+                platfrom_fee,
+                recipient_amount,
+                memo,
+                TipStatus::Escrowed,
+            )?;
             // Hold token in escrow and emit event for off-chain notification
             let escrow = Escrow {
                 sender: info.sender.clone(),
+                identifier: identifier.clone(),
                 amount: Coin {
                     denom: amount.denom.clone(),
                     amount: recipient_amount,
                 },
+                tip_id,
+                expires_at: env.block.time.plus_seconds(config.claim_link_ttl),
+                secret_hash,
             };
-            ESCROWS.save(deps.storage, identifier.to_string(), &escrow)?;
-            let event = Event::new("unregistered transfer")
-                    .add_attribute("identifier", &identifier)
-                    .add_attribute("sender", info.sender.to_string())
-                    .add_attribute("amount", amount.amount.to_string())
-                    .add_attribute("denom", amount.denom.clone());
-                Ok(create_response(
-                    "escrow",
-                    vec![
-                        ("identifier", &identifier),
-                        ("sender", info.sender.as_ref()),
-                        ("amount", &amount.amount.to_string()),
-                        ("fee", &platfrom_fee.to_string()),
-                    ],
-                )
-                .add_event(event)
-                .add_message(fee_msg))       
+            escrows().save(deps.storage, tip_id, &escrow)?;
+            let claim_id = claim_id(&env.contract.address, tip_id);
+            let commitment = claim_commitment(&claim_id, &identifier, &escrow.amount, escrow.expires_at);
+            Ok(create_response(TipEvent::Escrow {
+                tip_id,
+                sender: info.sender.to_string(),
+                identifier,
+                denom: amount.denom.clone(),
+                gross_amount: amount.amount,
+                fee: platfrom_fee,
+                net_amount: recipient_amount,
+                claim_id,
+                commitment,
+                expires_at: escrow.expires_at,
+            }))
         }
     }
 }
@@ -153,49 +891,703 @@ fn execute_transfer(
 fn execute_claim(
     deps: DepsMut,
     info: MessageInfo,
-    identifier: String
-) -> StdResult<Response> {
+    identifier: String,
+    destination: Option<String>,
+    denom: Option<String>,
+    amount: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.as_ref(), Operation::Claim)?;
     // validate identifier
     validate_identifier(identifier.clone())?;
+    // a zero partial claim would send an empty bank message
+    if amount.is_some_and(|amount| amount.is_zero()) {
+        return Err(ContractError::ZeroClaimAmount {});
+    }
+    // escrows of blocked identifiers stay frozen until force-refunded
+    assert_addr_not_blocked(deps.as_ref(), &info.sender)?;
+    assert_identifier_not_blocked(deps.as_ref(), &identifier)?;
     // check if Identifier is registered to the caller
     match ACCOUNTS.may_load(deps.storage, identifier.to_string())? {
         Some(addr) if addr == info.sender => {
+            // funds go to the caller unless routed elsewhere, e.g. a cold wallet
+            let destination = match destination {
+                Some(destination) => deps.api.addr_validate(&destination)?,
+                None => info.sender.clone(),
+            };
+            assert_addr_not_blocked(deps.as_ref(), &destination)?;
+            if amount.is_some() && denom.is_none() {
+                return Err(StdError::generic_err("Partial claims need a denom").into());
+            }
             // check for escrowed tokens
-            match ESCROWS.may_load(deps.storage, identifier.to_string())? {
-                Some(escrow) => {
-                    // Transfer escrow tokens
-                    let transfer_msg = BankMsg::Send {
-                        to_address: info.sender.to_string(),
-                        amount: vec![escrow.amount.clone()],
-                    };
-                    // remove escrow
-                    ESCROWS.remove(deps.storage, identifier.to_string());
-                    Ok(create_response(
-                        "claim",
-                        vec![
-                            ("identifier", &identifier),
-                            ("recipient", info.sender.as_ref()),
-                            ("amount", &escrow.amount.amount.to_string()),
-                        ],
-                    ).add_message(transfer_msg))   
+            let pending = claimable_escrows(deps.as_ref(), &identifier, denom.as_deref())?;
+            if pending.is_empty() {
+                return Err(StdError::generic_err("No escrowed tokens found").into());
+            }
+            release_escrows(deps, pending, &destination, amount)
+        }
+        _ => Err(StdError::generic_err("identifier is not registered to caller").into()),
+    }
+}
+
+// Record a commitment to claim an escrow by secret. Anyone may commit, the
+// commitment only counts if it matches the secret and recipient revealed later.
+fn execute_commit_claim(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    escrow_id: u64,
+    commitment: String,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.as_ref(), Operation::Claim)?;
+    let commitment = commitment.to_lowercase();
+    validate_secret_hash(&commitment)
+        .map_err(|_| StdError::generic_err("Claim commitment must be a hex encoded sha256"))?;
+    let escrow = escrows()
+        .may_load(deps.storage, escrow_id)?
+        .ok_or_else(|| StdError::generic_err("No escrowed tokens found"))?;
+    if escrow.secret_hash.is_none() {
+        return Err(StdError::generic_err("Escrow has no claim secret").into());
+    }
+    if env.block.time >= escrow.expires_at {
+        return Err(StdError::generic_err("Claim link has expired").into());
+    }
+    CLAIM_COMMITS.save(deps.storage, (escrow_id, commitment), &env.block.height)?;
+    Ok(create_response(TipEvent::ClaimCommit {
+        escrow_id,
+        committer: info.sender.to_string(),
+    }))
+}
+
+// Release an escrow to whoever knows the secret behind its hash. The secret is
+// revealed by this transaction, so a claim link only works once, and only for
+// the recipient committed to in an earlier block.
+fn execute_claim_with_secret(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    escrow_id: u64,
+    secret: String,
+    recipient: Option<String>,
+    register: bool,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.as_ref(), Operation::Claim)?;
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender.clone(),
+    };
+    assert_addr_not_blocked(deps.as_ref(), &info.sender)?;
+    assert_addr_not_blocked(deps.as_ref(), &recipient)?;
+
+    let escrow = escrows()
+        .may_load(deps.storage, escrow_id)?
+        .ok_or_else(|| StdError::generic_err("No escrowed tokens found"))?;
+    assert_identifier_not_blocked(deps.as_ref(), &escrow.identifier)?;
+    match &escrow.secret_hash {
+        Some(hash) if *hash == secret_hash(&secret) => {}
+        Some(_) => return Err(StdError::generic_err("Invalid claim secret").into()),
+        None => return Err(StdError::generic_err("Escrow has no claim secret").into()),
+    }
+    if env.block.time >= escrow.expires_at {
+        return Err(StdError::generic_err("Claim link has expired").into());
+    }
+    // a commitment from this block could have been copied from the reveal
+    let commit = (escrow_id, claim_reveal_hash(&secret, &recipient));
+    match CLAIM_COMMITS.may_load(deps.storage, commit.clone())? {
+        Some(height) if height < env.block.height => CLAIM_COMMITS.remove(deps.storage, commit),
+        Some(_) => return Err(StdError::generic_err("Claim commitment must be from an earlier block").into()),
+        None => return Err(StdError::generic_err("No claim commitment for this secret and recipient").into()),
+    }
+
+    let mut response = Response::new();
+    if register {
+        response = register_identifier(deps.branch(), &env, escrow.identifier.clone(), &recipient, None)?;
+    }
+    let claim = release_escrows(deps, vec![escrow], &recipient, None)?;
+    Ok(response
+        .add_attributes(claim.attributes)
+        .add_events(claim.events)
+        .add_submessages(claim.messages))
+}
+
+// up to MAX_LIMIT pending escrows for an identifier that `keep` accepts,
+// oldest first. The filter applies before the limit, so escrows that are
+// not wanted never crowd out the ones that are.
+fn pending_escrows(deps: Deps, identifier: &str, keep: impl Fn(&Escrow) -> bool) -> StdResult<Vec<Escrow>> {
+    escrows()
+        .idx
+        .identifier
+        .prefix(identifier.to_string())
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| item.as_ref().map_or(true, |(_, escrow)| keep(escrow)))
+        .take(MAX_LIMIT as usize)
+        .map(|item| item.map(|(_, escrow)| escrow))
+        .collect()
+}
+
+// What whoever registered an identifier may claim, optionally in one denom.
+// Registering proves nothing about owning the email, so escrows with a claim
+// secret are left to their link and otherwise only go back to the sender.
+fn claimable_escrows(deps: Deps, identifier: &str, denom: Option<&str>) -> StdResult<Vec<Escrow>> {
+    pending_escrows(deps, identifier, |escrow| {
+        escrow.secret_hash.is_none() && denom.is_none_or(|denom| escrow.amount.denom == denom)
+    })
+}
+
+// Pay escrows out to the recipient in a single bank message.
+fn release_escrows(
+    deps: DepsMut,
+    pending: Vec<Escrow>,
+    recipient: &Addr,
+    limit: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let (events, total) = take_escrows(deps, pending, recipient, limit, Uint128::zero())?;
+    // Transfer escrow tokens
+    let delivered = total.to_string();
+    let transfer_msg = BankMsg::Send {
+        to_address: recipient.to_string(),
+        amount: total.into_vec(),
+    };
+    Ok(create_batch_response("claim", events)
+        .add_attribute("delivered", delivered)
+        .add_message(transfer_msg))
+}
+
+// Take escrows oldest first and return the claim events and the total taken.
+// With a `limit` only that much is taken and the last escrow touched keeps
+// whatever is left over. The first `withheld` taken goes to a relayer rather
+// than the recipient, so it is left out of the recipient's totals.
+fn take_escrows(
+    deps: DepsMut,
+    pending: Vec<Escrow>,
+    recipient: &Addr,
+    limit: Option<Uint128>,
+    mut withheld: Uint128,
+) -> Result<(Vec<TipEvent>, Coins), ContractError> {
+    if let Some(limit) = limit {
+        let available: Uint128 = pending.iter().map(|e| e.amount.amount).sum();
+        if available < limit {
+            return Err(StdError::generic_err("Not enough escrowed tokens").into());
+        }
+    }
+    let mut total = Coins::default();
+    let mut events = vec![];
+    let mut remaining = limit;
+    for mut escrow in pending {
+        let take = match remaining {
+            Some(left) if left.is_zero() => break,
+            Some(left) => left.min(escrow.amount.amount),
+            None => escrow.amount.amount,
+        };
+        remaining = remaining.map(|left| left - take);
+        let claimed = Coin::new(take, &escrow.amount.denom);
+        escrow.amount.amount -= take;
+        let settled = escrow.amount.amount.is_zero();
+        if settled {
+            escrows().remove(deps.storage, escrow.tip_id)?;
+            set_tip_status(deps.storage, escrow.tip_id, TipStatus::Claimed)?;
+        } else {
+            escrows().save(deps.storage, escrow.tip_id, &escrow)?;
+        }
+        let to_relayer = withheld.min(take);
+        withheld -= to_relayer;
+        let received = Coin::new(take - to_relayer, &escrow.amount.denom);
+        record_received(deps.storage, &escrow.identifier, &received, u64::from(settled))?;
+        total.add(claimed)?;
+        events.push(TipEvent::Claim {
+            tip_id: escrow.tip_id,
+            identifier: escrow.identifier,
+            recipient: recipient.to_string(),
+            denom: escrow.amount.denom,
+            net_amount: take,
+            remaining_amount: escrow.amount.amount,
+        });
+    }
+    Ok((events, total))
+}
+
+// Claim submitted by a relayer on behalf of a recipient without gas. The
+// recipient signs a ClaimPayload bound to this chain and contract with the
+// key behind their registered address; the per-account nonce stops replays.
+fn execute_relay_claim(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    payload: ClaimPayload,
+    scheme: SignatureScheme,
+    pubkey: Binary,
+    signature: Binary,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.as_ref(), Operation::Claim)?;
+    if payload.chain_id != env.block.chain_id || payload.contract != env.contract.address.as_str() {
+        return Err(StdError::generic_err("Claim payload is for another chain or contract").into());
+    }
+    let signer = verify_claim(deps.api, &payload, &scheme, &pubkey, &signature)?;
+    match ACCOUNTS.may_load(deps.storage, payload.identifier.clone())? {
+        Some(addr) if addr == signer => {}
+        _ => return Err(StdError::generic_err("identifier is not registered to signer").into()),
+    }
+    let nonce = RELAY_NONCES.may_load(deps.storage, signer.clone())?.unwrap_or_default();
+    if payload.nonce != nonce {
+        return Err(StdError::generic_err(format!("Invalid nonce, expected {}", nonce)).into());
+    }
+    if let Some(relayer) = &payload.relayer {
+        if *relayer != info.sender.as_str() {
+            return Err(ContractError::Unauthorized {});
+        }
+    }
+
+    let destination = match &payload.destination {
+        Some(destination) => deps.api.addr_validate(destination)?,
+        None => signer.clone(),
+    };
+    assert_addr_not_blocked(deps.as_ref(), &signer)?;
+    assert_addr_not_blocked(deps.as_ref(), &destination)?;
+    assert_addr_not_blocked(deps.as_ref(), &info.sender)?;
+    assert_identifier_not_blocked(deps.as_ref(), &payload.identifier)?;
+
+    // the relayer is paid out of the claim, never more than the configured cap
+    let config = CONFIG.load(deps.storage)?;
+    if payload.relayer_fee > config.max_relayer_fee {
+        return Err(StdError::generic_err("Relayer fee exceeds the configured cap").into());
+    }
+    let pending = claimable_escrows(deps.as_ref(), &payload.identifier, Some(&config.token_denom))?;
+    if pending.is_empty() {
+        return Err(StdError::generic_err("No escrowed tokens found").into());
+    }
+    let claimed: Uint128 = pending.iter().map(|escrow| escrow.amount.amount).sum();
+    let recipient_amount = claimed
+        .checked_sub(payload.relayer_fee)
+        .map_err(|_| StdError::generic_err("Relayer fee exceeds the claimed amount"))?;
+
+    RELAY_NONCES.save(deps.storage, signer.clone(), &(nonce + 1))?;
+    let (mut events, _) = take_escrows(deps, pending, &destination, None, payload.relayer_fee)?;
+
+    let mut msgs = vec![];
+    if !recipient_amount.is_zero() {
+        msgs.push(BankMsg::Send {
+            to_address: destination.to_string(),
+            amount: vec![Coin::new(recipient_amount, &config.token_denom)],
+        });
+    }
+    if !payload.relayer_fee.is_zero() {
+        msgs.push(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin::new(payload.relayer_fee, &config.token_denom)],
+        });
+    }
+    events.push(TipEvent::Relay {
+        identifier: payload.identifier,
+        signer: signer.to_string(),
+        relayer: info.sender.to_string(),
+        nonce,
+        denom: config.token_denom,
+        relayer_fee: payload.relayer_fee,
+    });
+    Ok(create_batch_response("claim", events).add_messages(msgs))
+}
+
+fn execute_refund(
+    deps: DepsMut,
+    info: MessageInfo,
+    identifier: String
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.as_ref(), Operation::Refund)?;
+    assert_addr_not_blocked(deps.as_ref(), &info.sender)?;
+    assert_identifier_not_blocked(deps.as_ref(), &identifier)?;
+    // only the original sender can take back an unclaimed escrow
+    let own = pending_escrows(deps.as_ref(), &identifier, |escrow| escrow.sender == info.sender)?;
+    if own.is_empty() {
+        if pending_escrows(deps.as_ref(), &identifier, |_| true)?.is_empty() {
+            return Err(StdError::generic_err("No escrowed tokens found").into());
+        }
+        return Err(StdError::generic_err("Only the escrow sender can refund").into());
+    }
+    refund_escrows(deps, own, false)
+}
+
+// remove escrows and send each one back to whoever tipped it
+fn refund_escrows(
+    deps: DepsMut,
+    pending: Vec<Escrow>,
+    forced: bool,
+) -> Result<Response, ContractError> {
+    let mut refunds: BTreeMap<Addr, Coins> = BTreeMap::new();
+    let mut events = vec![];
+    for escrow in pending {
+        escrows().remove(deps.storage, escrow.tip_id)?;
+        set_tip_status(deps.storage, escrow.tip_id, TipStatus::Refunded)?;
+        refunds.entry(escrow.sender.clone()).or_default().add(escrow.amount.clone())?;
+        events.push(TipEvent::Refund {
+            tip_id: escrow.tip_id,
+            identifier: escrow.identifier,
+            sender: escrow.sender.to_string(),
+            denom: escrow.amount.denom,
+            net_amount: escrow.amount.amount,
+            forced,
+        });
+    }
+    let refund_msgs = refunds.into_iter().map(|(sender, amount)| BankMsg::Send {
+        to_address: sender.to_string(),
+        amount: amount.into_vec(),
+    });
+    Ok(create_batch_response("refund", events).add_messages(refund_msgs))
+}
+
+// Send a referrer everything they have earned and not yet claimed.
+fn execute_claim_referral_rewards(
+    deps: DepsMut,
+    info: MessageInfo,
+    identifier: String,
+) -> Result<Response, ContractError> {
+    match ACCOUNTS.may_load(deps.storage, identifier.clone())? {
+        Some(owner) if owner == info.sender => {}
+        _ => return Err(ContractError::Unauthorized {}),
+    }
+    assert_addr_not_blocked(deps.as_ref(), &info.sender)?;
+    assert_identifier_not_blocked(deps.as_ref(), &identifier)?;
+    let earnings = REFERRAL_EARNINGS
+        .prefix(identifier.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut payout = Coins::default();
+    let mut events = vec![];
+    for (denom, mut earned) in earnings {
+        if earned.unpaid.is_zero() {
+            continue;
+        }
+        payout.add(Coin::new(earned.unpaid, &denom))?;
+        events.push(TipEvent::ReferralPayout {
+            referrer: identifier.clone(),
+            recipient: info.sender.to_string(),
+            denom: denom.clone(),
+            amount: earned.unpaid,
+        });
+        earned.unpaid = Uint128::zero();
+        REFERRAL_EARNINGS.save(deps.storage, (identifier.clone(), denom), &earned)?;
+    }
+    if payout.is_empty() {
+        return Err(StdError::generic_err("No referral rewards to claim").into());
+    }
+    Ok(create_batch_response("claim_referral_rewards", events).add_message(BankMsg::Send {
+        to_address: info.sender.to_string(),
+        amount: payout.into_vec(),
+    }))
+}
+
+// Pay the whole fee pool out to the fee wallets, one bank message per wallet.
+fn execute_withdraw_fees(deps: DepsMut) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let payouts = drain(deps.storage, &config.fee_shares)?;
+    if payouts.is_empty() {
+        return Err(StdError::generic_err("No fees to withdraw").into());
+    }
+    let mut totals: BTreeMap<Addr, Coins> = BTreeMap::new();
+    let mut events = vec![];
+    for (share, coin) in payouts {
+        totals.entry(share.address.clone()).or_default().add(coin.clone())?;
+        events.push(TipEvent::FeeWithdrawal {
+            recipient: share.address.to_string(),
+            denom: coin.denom,
+            amount: coin.amount,
+            bps: share.bps,
+        });
+    }
+    let msgs = totals.into_iter().map(|(wallet, amount)| BankMsg::Send {
+        to_address: wallet.to_string(),
+        amount: amount.into_vec(),
+    });
+    Ok(create_batch_response("withdraw_fees", events).add_messages(msgs))
+}
+
+fn execute_set_fee_shares(
+    deps: DepsMut,
+    info: MessageInfo,
+    shares: Vec<FeeShareMsg>,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+    let mut config = CONFIG.load(deps.storage)?;
+    config.fee_shares = checked_fee_shares(deps.as_ref(), shares)?;
+    validate_config(deps.as_ref(), &config)?;
+    CONFIG.save(deps.storage, &config)?;
+    Ok(create_response(TipEvent::FeeShares {
+        shares: config.fee_shares.iter().map(|s| format!("{}:{}", s.address, s.bps)).collect(),
+        admin: info.sender.to_string(),
+    }))
+}
+
+fn execute_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    update: ConfigUpdate,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+    apply_config_update(deps, update)?;
+    Ok(create_response(TipEvent::UpdateConfig { admin: info.sender.to_string() }))
+}
+
+// shared by UpdateConfig and the governance override
+fn apply_config_update(deps: DepsMut, update: ConfigUpdate) -> Result<(), ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if let Some(admin) = update.admin {
+        config.admin = deps.api.addr_validate(&admin)?;
+    }
+    if let Some(guardian) = update.guardian {
+        config.guardian = Some(deps.api.addr_validate(&guardian)?);
+    }
+    if let Some(platform_wallet) = update.platform_wallet {
+        let platform_wallet = deps.api.addr_validate(&platform_wallet)?;
+        // fees follow the wallet while all of them go to it; a custom split
+        // stays as it is
+        if config.fee_shares == default_fee_shares(&config.platform_wallet) {
+            config.fee_shares = default_fee_shares(&platform_wallet);
+        }
+        config.platform_wallet = platform_wallet;
+    }
+    if let Some(shares) = update.fee_shares {
+        config.fee_shares = checked_fee_shares(deps.as_ref(), shares)?;
+    }
+    if let Some(tip_retention) = update.tip_retention {
+        config.tip_retention = tip_retention;
+    }
+    if let Some(rate_limits) = update.rate_limits {
+        config.rate_limits = rate_limits;
+    }
+    if let Some(claim_link_ttl) = update.claim_link_ttl {
+        config.claim_link_ttl = claim_link_ttl;
+    }
+    if let Some(max_relayer_fee) = update.max_relayer_fee {
+        config.max_relayer_fee = max_relayer_fee;
+    }
+    if let Some(referral) = update.referral {
+        config.referral = referral;
+    }
+    if let Some(threshold) = update.micro_tip_threshold {
+        config.micro_tip_threshold = threshold;
+    }
+    validate_config(deps.as_ref(), &config)?;
+    CONFIG.save(deps.storage, &config)?;
+    Ok(())
+}
+
+fn execute_force_refund(
+    deps: DepsMut,
+    info: MessageInfo,
+    identifier: String,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+    // forced refunds are only for escrows frozen by the denylist
+    if !BLOCKED_IDENTIFIERS.has(deps.storage, identifier.clone()) {
+        return Err(StdError::generic_err("Identifier is not blocked").into());
+    }
+    let pending = pending_escrows(deps.as_ref(), &identifier, |_| true)?;
+    if pending.is_empty() {
+        return Err(StdError::generic_err("No escrowed tokens found").into());
+    }
+    refund_escrows(deps, pending, true)
+}
+
+fn execute_block(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    target: BlockTarget,
+    reason: Option<String>,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+    let entry = BlockEntry {
+        reason: reason.clone(),
+        since: env.block.time,
+    };
+    let (kind, value) = match target {
+        BlockTarget::Address(addr) => {
+            let addr = deps.api.addr_validate(&addr)?;
+            BLOCKED_ADDRESSES.save(deps.storage, addr.clone(), &entry)?;
+            ("address", addr.to_string())
+        }
+        BlockTarget::Identifier(identifier) => {
+            BLOCKED_IDENTIFIERS.save(deps.storage, identifier.clone(), &entry)?;
+            ("identifier", identifier)
+        }
+    };
+    Ok(create_response(TipEvent::Block {
+        kind: kind.to_string(),
+        target: value,
+        reason,
+        admin: info.sender.to_string(),
+    }))
+}
+
+fn execute_unblock(
+    deps: DepsMut,
+    info: MessageInfo,
+    target: BlockTarget,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+    let (kind, value) = match target {
+        BlockTarget::Address(addr) => {
+            let addr = deps.api.addr_validate(&addr)?;
+            BLOCKED_ADDRESSES.remove(deps.storage, addr.clone());
+            ("address", addr.to_string())
+        }
+        BlockTarget::Identifier(identifier) => {
+            BLOCKED_IDENTIFIERS.remove(deps.storage, identifier.clone());
+            ("identifier", identifier)
+        }
+    };
+    Ok(create_response(TipEvent::Unblock {
+        kind: kind.to_string(),
+        target: value,
+        admin: info.sender.to_string(),
+    }))
+}
+
+fn execute_set_pause(
+    deps: DepsMut,
+    info: MessageInfo,
+    operations: Vec<Operation>,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let is_admin = info.sender == config.admin;
+    let is_guardian = config.guardian.as_ref() == Some(&info.sender);
+    // the guardian can only stop inflows, never unpause or lock up escrowed funds
+    let guardian_allowed = paused
+        && operations
+            .iter()
+            .all(|op| matches!(op, Operation::Register | Operation::Transfer));
+    if !(is_admin || (is_guardian && guardian_allowed)) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    set_paused(deps, &operations, paused)?;
+    let operations = operations.iter().map(|op| op.to_string()).collect();
+    let sender = info.sender.to_string();
+    Ok(create_response(if paused {
+        TipEvent::Pause { operations, sender }
+    } else {
+        TipEvent::Unpause { operations, sender }
+    }))
+}
+
+fn set_paused(deps: DepsMut, operations: &[Operation], paused: bool) -> StdResult<()> {
+    let mut state = PAUSE.may_load(deps.storage)?.unwrap_or_default();
+    for op in operations {
+        state.set(*op, paused);
+    }
+    PAUSE.save(deps.storage, &state)
+}
+
+// Chain governance overrides, the way back in if the admin key is lost. They
+// skip the admin checks but not the config validation.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::UpdateConfig(update) => {
+            apply_config_update(deps, update)?;
+            Ok(create_response(TipEvent::UpdateConfig { admin: GOVERNANCE.to_string() }))
+        }
+        SudoMsg::Unpause { operations } => {
+            set_paused(deps, &operations, false)?;
+            Ok(create_response(TipEvent::Unpause {
+                operations: operations.iter().map(|op| op.to_string()).collect(),
+                sender: GOVERNANCE.to_string(),
+            }))
+        }
+        SudoMsg::ForceRefund { escrow_ids } => {
+            let mut pending: Vec<Escrow> = vec![];
+            for tip_id in escrow_ids {
+                if pending.iter().any(|e| e.tip_id == tip_id) {
+                    continue;
                 }
-                None => Err(StdError::generic_err("No escrowed tokens found")),
+                let escrow = escrows()
+                    .may_load(deps.storage, tip_id)?
+                    .ok_or_else(|| StdError::generic_err(format!("Escrow {} not found", tip_id)))?;
+                pending.push(escrow);
+            }
+            if pending.is_empty() {
+                return Err(StdError::generic_err("No escrowed tokens found").into());
             }
+            refund_escrows(deps, pending, true)
         }
-        _ => Err(StdError::generic_err("identifier is not registered to caller")),
     }
 }
 
 #[entry_point]
 pub fn query(
     deps: Deps,
-    _env: Env,
+    env: Env,
     msg: QueryMsg,
 ) -> StdResult<Binary>{
     match msg {
         QueryMsg::GetBalance { address } => to_json_binary(&query_balance(deps, address)?),
         QueryMsg::GetEscrow { identifier } => to_json_binary(&query_escrow(deps, identifier)?),
+        QueryMsg::GetEscrows { identifier, start_after, limit } => to_json_binary(&query_escrows(deps, identifier, start_after, limit)?),
+        QueryMsg::GetEscrowById { escrow_id } => to_json_binary(&EscrowResponse { escrow: escrows().may_load(deps.storage, escrow_id)? }),
         QueryMsg::GetAccount { identifier } =>  to_json_binary(&query_account(deps, identifier)?),
+        QueryMsg::GetTip { id } => to_json_binary(&query_tip(deps, id)?),
+        QueryMsg::TipsSent { sender, start_after, limit } => to_json_binary(&query_tips_sent(deps, sender, start_after, limit)?),
+        QueryMsg::TipsReceived { identifier, start_after, limit } => to_json_binary(&query_tips_received(deps, identifier, start_after, limit)?),
+        QueryMsg::RelayNonce { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            let nonce = RELAY_NONCES.may_load(deps.storage, address)?.unwrap_or_default();
+            to_json_binary(&RelayNonceResponse { nonce })
+        }
+        QueryMsg::GetReferral { identifier } => {
+            let referral = REFERRALS.may_load(deps.storage, identifier)?;
+            let duration = CONFIG.load(deps.storage)?.referral.duration;
+            let rewards_until = referral.as_ref().map(|r| r.since.plus_seconds(duration));
+            to_json_binary(&ReferralResponse { referral, rewards_until })
+        }
+        QueryMsg::ReferralEarnings { identifier } => {
+            let earnings = REFERRAL_EARNINGS
+                .prefix(identifier)
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| {
+                    item.map(|(denom, e)| ReferralEarning { denom, earned: e.earned, unpaid: e.unpaid })
+                })
+                .collect::<StdResult<Vec<_>>>()?;
+            to_json_binary(&ReferralEarningsResponse { earnings })
+        }
+        QueryMsg::PendingMicroTips { identifier } => {
+            let pending = MICRO_TIP_BUCKETS
+                .prefix(identifier)
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(denom, b)| PendingMicroTips { denom, gross: b.gross, tips: b.tips }))
+                .collect::<StdResult<Vec<_>>>()?;
+            to_json_binary(&MicroTipsResponse { pending })
+        }
+        QueryMsg::PayoutPolicy { identifier } => to_json_binary(&PayoutPolicyResponse {
+            policy: PAYOUT_POLICIES.may_load(deps.storage, identifier)?.unwrap_or_default(),
+        }),
+        QueryMsg::Allowances { owner, start_after, limit } => to_json_binary(&query_allowances(deps, owner, start_after, limit)?),
+        QueryMsg::InternalBalance { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            let balances = BALANCES
+                .prefix(address)
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(denom, amount)| Coin::new(amount, denom)))
+                .collect::<StdResult<Vec<_>>>()?;
+            to_json_binary(&InternalBalanceResponse { balances })
+        }
+        QueryMsg::GetProfile { identifier } => to_json_binary(&ProfileResponse {
+            address: ACCOUNTS.may_load(deps.storage, identifier.clone())?,
+            profile: PROFILES.may_load(deps.storage, identifier.clone())?,
+            identifier,
+        }),
+        QueryMsg::Config {} => to_json_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::Snapshot {} => to_json_binary(&snapshot::header(deps, &env)?),
+        QueryMsg::SnapshotPage { section, start_after, limit } => {
+            to_json_binary(&snapshot::page(deps, &env, section, start_after, limit)?)
+        }
+        QueryMsg::FeePool {} => to_json_binary(&FeePoolResponse {
+            accrued: accrued(deps.storage)?,
+            shares: CONFIG.load(deps.storage)?.fee_shares,
+        }),
+        QueryMsg::PauseStatus {} => to_json_binary(&PauseResponse { paused: PAUSE.may_load(deps.storage)?.unwrap_or_default() }),
+        QueryMsg::IsBlocked { target } => to_json_binary(&query_blocked(deps, target)?),
+        QueryMsg::Stats { denom } => to_json_binary(&query_stats(deps, denom)?),
+        QueryMsg::TopRecipients { denom, limit } => to_json_binary(&query_leaderboard(deps, received_totals(), denom, limit)?),
+        QueryMsg::TopSenders { denom, limit } => to_json_binary(&query_leaderboard(deps, sent_totals(), denom, limit)?),
     }
 }
 
@@ -204,7 +1596,7 @@ fn query_balance(
     address: String
 ) -> StdResult<BalanceResponse> {
     let addr = deps.api.addr_validate(&address)?;
-    let balance = deps.querier.query_balance(addr, TOKEN_DENOM.load(deps.storage)?)?;
+    let balance = deps.querier.query_balance(addr, CONFIG.load(deps.storage)?.token_denom)?;
     Ok(BalanceResponse { balance })
 }
 
@@ -212,10 +1604,66 @@ fn query_escrow(
     deps: Deps,
     identifier: String,
 ) -> StdResult<EscrowResponse> {
-    let escrow = ESCROWS.may_load(deps.storage, identifier)?;
+    // oldest pending escrow for the identifier
+    let escrow = escrows()
+        .idx
+        .identifier
+        .prefix(identifier)
+        .range(deps.storage, None, None, Order::Ascending)
+        .next()
+        .transpose()?
+        .map(|(_, escrow)| escrow);
     Ok(EscrowResponse { escrow })
 }
 
+fn query_escrows(
+    deps: Deps,
+    identifier: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<EscrowsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let min = start_after.map(Bound::exclusive);
+    let escrows = escrows()
+        .idx
+        .identifier
+        .prefix(identifier)
+        .range(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, escrow)| escrow))
+        .collect::<StdResult<_>>()?;
+    Ok(EscrowsResponse { escrows })
+}
+
+fn query_allowances(
+    deps: Deps,
+    owner: String,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<AllowancesResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let min = match start_after {
+        Some((spender, denom)) => Some(Bound::exclusive((deps.api.addr_validate(&spender)?, denom))),
+        None => None,
+    };
+    let allowances = ALLOWANCES
+        .sub_prefix(owner)
+        .range(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|((spender, denom), allowance)| AllowanceInfo {
+                spender: spender.to_string(),
+                denom,
+                remaining: allowance.remaining,
+                per_tip_cap: allowance.per_tip_cap,
+                expires: allowance.expires,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(AllowancesResponse { allowances })
+}
+
 fn query_account(
     deps: Deps,
     identifier: String,
@@ -224,3 +1672,99 @@ fn query_account(
     Ok(AccountResponse { address }) 
 }
 
+fn query_tip(
+    deps: Deps,
+    id: u64,
+) -> StdResult<TipResponse> {
+    let tip = tips().may_load(deps.storage, id)?;
+    Ok(TipResponse { tip })
+}
+
+fn query_tips_sent(
+    deps: Deps,
+    sender: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<TipsResponse> {
+    let sender = deps.api.addr_validate(&sender)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let max = start_after.map(Bound::exclusive);
+    let tips = tips()
+        .idx
+        .sender
+        .prefix(sender)
+        .range(deps.storage, None, max, Order::Descending)
+        .take(limit)
+        .map(|item| item.map(|(_, tip)| tip))
+        .collect::<StdResult<_>>()?;
+    Ok(TipsResponse { tips })
+}
+
+fn query_tips_received(
+    deps: Deps,
+    identifier: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<TipsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let max = start_after.map(Bound::exclusive);
+    let tips = tips()
+        .idx
+        .recipient
+        .prefix(identifier)
+        .range(deps.storage, None, max, Order::Descending)
+        .take(limit)
+        .map(|item| item.map(|(_, tip)| tip))
+        .collect::<StdResult<_>>()?;
+    Ok(TipsResponse { tips })
+}
+
+fn query_blocked(
+    deps: Deps,
+    target: BlockTarget,
+) -> StdResult<BlockedResponse> {
+    let entry = match target {
+        BlockTarget::Address(addr) => {
+            BLOCKED_ADDRESSES.may_load(deps.storage, deps.api.addr_validate(&addr)?)?
+        }
+        BlockTarget::Identifier(identifier) => BLOCKED_IDENTIFIERS.may_load(deps.storage, identifier)?,
+    };
+    Ok(BlockedResponse { entry })
+}
+
+fn query_stats(
+    deps: Deps,
+    denom: String,
+) -> StdResult<StatsResponse> {
+    let stats = DENOM_STATS.may_load(deps.storage, denom.clone())?.unwrap_or_default();
+    Ok(StatsResponse {
+        denom,
+        volume: stats.volume,
+        tips: stats.tips,
+    })
+}
+
+fn query_leaderboard(
+    deps: Deps,
+    totals: IndexedMap<(String, String), TipTotal, TotalIndexes>,
+    denom: String,
+    limit: Option<u32>,
+) -> StdResult<LeaderboardResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    // walk the (denom, amount) index from the top instead of scanning every total
+    let entries = totals
+        .idx
+        .amount
+        .sub_prefix(denom)
+        .range(deps.storage, None, None, Order::Descending)
+        .take(limit)
+        .map(|item| {
+            item.map(|((account, _), total)| LeaderboardEntry {
+                account,
+                amount: total.amount,
+                count: total.count,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(LeaderboardResponse { entries })
+}
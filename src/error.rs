@@ -1,13 +1,31 @@
 use cosmwasm_std::StdError;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
     #[error("Unauthorized")]
     Unauthorized {},
+
+    #[error("{operation} is paused")]
+    Paused { operation: String },
+
+    #[error("{target} is blocked")]
+    Blocked { target: String },
+
+    #[error("Tip is below the minimum of {min}")]
+    TipTooSmall { min: String },
+
+    #[error("Too many escrowed tips sent recently, try again later")]
+    SenderRateLimited {},
+
+    #[error("Too many escrowed tips sent to {identifier} recently, try again later")]
+    InboundCapReached { identifier: String },
+
+    #[error("Claim amount must be positive")]
+    ZeroClaimAmount {},
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
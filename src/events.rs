@@ -0,0 +1,884 @@
+use cosmwasm_std::{Event, Timestamp, Uint128};
+
+// Every action emits an event of type `social_tip.<action>`, one per tip it
+// touches. The chain prefixes custom event types with `wasm-`, so indexers see
+// `wasm-social_tip.<action>`.
+pub const EVENT_NAMESPACE: &str = "social_tip";
+
+// Bumped whenever an attribute is renamed or removed. New attributes may be
+// appended within a version; indexers should ignore keys they don't know.
+pub const EVENT_SCHEMA_VERSION: &str = "1";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TipEvent {
+    Instantiate {
+        admin: String,
+        token_denom: String,
+        platform_wallet: String,
+    },
+    Register {
+        identifier: String,
+        address: String,
+        // identifier that referred this one, empty if none
+        referrer: Option<String>,
+    },
+    // profile set or removed by the identifier's owner
+    Profile {
+        identifier: String,
+        address: String,
+        removed: bool,
+    },
+    // tip delivered straight to a registered recipient
+    Transfer {
+        tip_id: u64,
+        sender: String,
+        identifier: String,
+        recipient: String,
+        denom: String,
+        gross_amount: Uint128,
+        fee: Uint128,
+        net_amount: Uint128,
+        // recipient's payout policy: direct, forward, split or hold
+        payout: String,
+    },
+    PayoutPolicy {
+        identifier: String,
+        policy: String,
+        owner: String,
+    },
+    // funds moved into an internal balance
+    Deposit {
+        address: String,
+        denom: String,
+        amount: Uint128,
+    },
+    // internal balance paid out to its owner
+    Withdraw {
+        address: String,
+        denom: String,
+        amount: Uint128,
+    },
+    // allowance granted to a spender; zero `amount` with `revoked` set when taken back
+    Approval {
+        owner: String,
+        spender: String,
+        denom: String,
+        amount: Uint128,
+        per_tip_cap: Option<Uint128>,
+        expires: Option<Timestamp>,
+        revoked: bool,
+    },
+    // tip sent by a spender out of an owner's balance, next to its Transfer or Escrow
+    TransferFrom {
+        owner: String,
+        spender: String,
+        denom: String,
+        amount: Uint128,
+        remaining: Uint128,
+    },
+    // tip below the micro-tip threshold added to its recipient's bucket
+    MicroTip {
+        tip_id: u64,
+        sender: String,
+        identifier: String,
+        denom: String,
+        amount: Uint128,
+        // bucket total after this tip
+        pending_amount: Uint128,
+    },
+    // one bucket paid out by Settle, fee charged on the whole bucket
+    Settle {
+        identifier: String,
+        recipient: String,
+        denom: String,
+        tips: u32,
+        gross_amount: Uint128,
+        fee: Uint128,
+        net_amount: Uint128,
+        payout: String,
+    },
+    // tip held for an unregistered identifier, carries the claim link token
+    // the off-chain mailer needs
+    Escrow {
+        tip_id: u64,
+        sender: String,
+        identifier: String,
+        denom: String,
+        gross_amount: Uint128,
+        fee: Uint128,
+        net_amount: Uint128,
+        claim_id: String,
+        commitment: String,
+        expires_at: Timestamp,
+    },
+    // claim by secret committed to, revealed in a later block
+    ClaimCommit {
+        escrow_id: u64,
+        committer: String,
+    },
+    Claim {
+        tip_id: u64,
+        identifier: String,
+        recipient: String,
+        denom: String,
+        net_amount: Uint128,
+        // left in escrow after a partial claim
+        remaining_amount: Uint128,
+    },
+    Refund {
+        tip_id: u64,
+        identifier: String,
+        sender: String,
+        denom: String,
+        net_amount: Uint128,
+        // true when the admin returned a frozen escrow
+        forced: bool,
+    },
+    // relayer paid out of a claim signed by the recipient
+    Relay {
+        identifier: String,
+        signer: String,
+        relayer: String,
+        nonce: u64,
+        denom: String,
+        relayer_fee: Uint128,
+    },
+    // referrer's cut of the fee on a tip to someone they referred
+    ReferralReward {
+        tip_id: u64,
+        referrer: String,
+        referred: String,
+        denom: String,
+        amount: Uint128,
+    },
+    // unpaid referral rewards sent to the referrer
+    ReferralPayout {
+        referrer: String,
+        recipient: String,
+        denom: String,
+        amount: Uint128,
+    },
+    // one wallet's cut of the fee pool in one denom
+    FeeWithdrawal {
+        recipient: String,
+        denom: String,
+        amount: Uint128,
+        bps: u16,
+    },
+    UpdateConfig {
+        admin: String,
+    },
+    Migrate {
+        from_version: String,
+        to_version: String,
+    },
+    // new fee split, each share formatted as `address:bps`
+    FeeShares {
+        shares: Vec<String>,
+        admin: String,
+    },
+    Pause {
+        operations: Vec<String>,
+        sender: String,
+    },
+    Unpause {
+        operations: Vec<String>,
+        sender: String,
+    },
+    Block {
+        kind: String,
+        target: String,
+        reason: Option<String>,
+        admin: String,
+    },
+    Unblock {
+        kind: String,
+        target: String,
+        admin: String,
+    },
+}
+
+impl TipEvent {
+    pub fn action(&self) -> &'static str {
+        match self {
+            TipEvent::Instantiate { .. } => "instantiate",
+            TipEvent::Register { .. } => "register",
+            TipEvent::Profile { .. } => "profile",
+            TipEvent::Transfer { .. } => "transfer",
+            TipEvent::PayoutPolicy { .. } => "payout_policy",
+            TipEvent::Deposit { .. } => "deposit",
+            TipEvent::Withdraw { .. } => "withdraw",
+            TipEvent::Approval { .. } => "approval",
+            TipEvent::TransferFrom { .. } => "transfer_from",
+            TipEvent::MicroTip { .. } => "micro_tip",
+            TipEvent::Settle { .. } => "settle",
+            TipEvent::Escrow { .. } => "escrow",
+            TipEvent::ClaimCommit { .. } => "claim_commit",
+            TipEvent::Claim { .. } => "claim",
+            TipEvent::Refund { .. } => "refund",
+            TipEvent::Relay { .. } => "relay",
+            TipEvent::ReferralReward { .. } => "referral_reward",
+            TipEvent::ReferralPayout { .. } => "referral_payout",
+            TipEvent::FeeWithdrawal { .. } => "fee_withdrawal",
+            TipEvent::FeeShares { .. } => "fee_shares",
+            TipEvent::UpdateConfig { .. } => "update_config",
+            TipEvent::Migrate { .. } => "migrate",
+            TipEvent::Pause { .. } => "pause",
+            TipEvent::Unpause { .. } => "unpause",
+            TipEvent::Block { .. } => "block",
+            TipEvent::Unblock { .. } => "unblock",
+        }
+    }
+
+    // full event type, without the `wasm-` prefix added by the chain
+    pub fn event_type(&self) -> String {
+        format!("{}.{}", EVENT_NAMESPACE, self.action())
+    }
+}
+
+impl From<TipEvent> for Event {
+    fn from(tip_event: TipEvent) -> Self {
+        let event = Event::new(tip_event.event_type())
+            .add_attribute("schema_version", EVENT_SCHEMA_VERSION);
+        match tip_event {
+            TipEvent::Instantiate { admin, token_denom, platform_wallet } => event
+                .add_attribute("admin", admin)
+                .add_attribute("token_denom", token_denom)
+                .add_attribute("platform_wallet", platform_wallet),
+            TipEvent::Register { identifier, address, referrer } => event
+                .add_attribute("identifier", identifier)
+                .add_attribute("address", address)
+                .add_attribute("referrer", referrer.unwrap_or_default()),
+            TipEvent::Profile { identifier, address, removed } => event
+                .add_attribute("identifier", identifier)
+                .add_attribute("address", address)
+                .add_attribute("removed", removed.to_string()),
+            TipEvent::Transfer {
+                tip_id,
+                sender,
+                identifier,
+                recipient,
+                denom,
+                gross_amount,
+                fee,
+                net_amount,
+                payout,
+            } => event
+                .add_attribute("tip_id", tip_id.to_string())
+                .add_attribute("sender", sender)
+                .add_attribute("identifier", identifier)
+                .add_attribute("recipient", recipient)
+                .add_attribute("denom", denom)
+                .add_attribute("gross_amount", gross_amount)
+                .add_attribute("fee", fee)
+                .add_attribute("net_amount", net_amount)
+                .add_attribute("payout", payout),
+            TipEvent::PayoutPolicy { identifier, policy, owner } => event
+                .add_attribute("identifier", identifier)
+                .add_attribute("policy", policy)
+                .add_attribute("owner", owner),
+            TipEvent::Deposit { address, denom, amount } | TipEvent::Withdraw { address, denom, amount } => event
+                .add_attribute("address", address)
+                .add_attribute("denom", denom)
+                .add_attribute("amount", amount),
+            TipEvent::Approval { owner, spender, denom, amount, per_tip_cap, expires, revoked } => event
+                .add_attribute("owner", owner)
+                .add_attribute("spender", spender)
+                .add_attribute("denom", denom)
+                .add_attribute("amount", amount)
+                .add_attribute("per_tip_cap", per_tip_cap.map(|cap| cap.to_string()).unwrap_or_default())
+                .add_attribute("expires", expires.map(|t| t.seconds().to_string()).unwrap_or_default())
+                .add_attribute("revoked", revoked.to_string()),
+            TipEvent::TransferFrom { owner, spender, denom, amount, remaining } => event
+                .add_attribute("owner", owner)
+                .add_attribute("spender", spender)
+                .add_attribute("denom", denom)
+                .add_attribute("amount", amount)
+                .add_attribute("remaining", remaining),
+            TipEvent::MicroTip { tip_id, sender, identifier, denom, amount, pending_amount } => event
+                .add_attribute("tip_id", tip_id.to_string())
+                .add_attribute("sender", sender)
+                .add_attribute("identifier", identifier)
+                .add_attribute("denom", denom)
+                .add_attribute("amount", amount)
+                .add_attribute("pending_amount", pending_amount),
+            TipEvent::Settle { identifier, recipient, denom, tips, gross_amount, fee, net_amount, payout } => event
+                .add_attribute("identifier", identifier)
+                .add_attribute("recipient", recipient)
+                .add_attribute("denom", denom)
+                .add_attribute("tips", tips.to_string())
+                .add_attribute("gross_amount", gross_amount)
+                .add_attribute("fee", fee)
+                .add_attribute("net_amount", net_amount)
+                .add_attribute("payout", payout),
+            TipEvent::Escrow {
+                tip_id,
+                sender,
+                identifier,
+                denom,
+                gross_amount,
+                fee,
+                net_amount,
+                claim_id,
+                commitment,
+                expires_at,
+            } => event
+                .add_attribute("tip_id", tip_id.to_string())
+                .add_attribute("sender", sender)
+                .add_attribute("identifier", identifier)
+                .add_attribute("denom", denom)
+                .add_attribute("gross_amount", gross_amount)
+                .add_attribute("fee", fee)
+                .add_attribute("net_amount", net_amount)
+                .add_attribute("claim_id", claim_id)
+                .add_attribute("commitment", commitment)
+                .add_attribute("expires_at", expires_at.seconds().to_string()),
+            TipEvent::ClaimCommit { escrow_id, committer } => event
+                .add_attribute("escrow_id", escrow_id.to_string())
+                .add_attribute("committer", committer),
+            TipEvent::Claim { tip_id, identifier, recipient, denom, net_amount, remaining_amount } => event
+                .add_attribute("tip_id", tip_id.to_string())
+                .add_attribute("identifier", identifier)
+                .add_attribute("recipient", recipient)
+                .add_attribute("denom", denom)
+                .add_attribute("net_amount", net_amount)
+                .add_attribute("remaining_amount", remaining_amount),
+            TipEvent::Refund { tip_id, identifier, sender, denom, net_amount, forced } => event
+                .add_attribute("tip_id", tip_id.to_string())
+                .add_attribute("identifier", identifier)
+                .add_attribute("sender", sender)
+                .add_attribute("denom", denom)
+                .add_attribute("net_amount", net_amount)
+                .add_attribute("forced", forced.to_string()),
+            TipEvent::Relay { identifier, signer, relayer, nonce, denom, relayer_fee } => event
+                .add_attribute("identifier", identifier)
+                .add_attribute("signer", signer)
+                .add_attribute("relayer", relayer)
+                .add_attribute("nonce", nonce.to_string())
+                .add_attribute("denom", denom)
+                .add_attribute("relayer_fee", relayer_fee),
+            TipEvent::ReferralReward { tip_id, referrer, referred, denom, amount } => event
+                .add_attribute("tip_id", tip_id.to_string())
+                .add_attribute("referrer", referrer)
+                .add_attribute("referred", referred)
+                .add_attribute("denom", denom)
+                .add_attribute("amount", amount),
+            TipEvent::ReferralPayout { referrer, recipient, denom, amount } => event
+                .add_attribute("referrer", referrer)
+                .add_attribute("recipient", recipient)
+                .add_attribute("denom", denom)
+                .add_attribute("amount", amount),
+            TipEvent::FeeWithdrawal { recipient, denom, amount, bps } => event
+                .add_attribute("recipient", recipient)
+                .add_attribute("denom", denom)
+                .add_attribute("amount", amount)
+                .add_attribute("bps", bps.to_string()),
+            TipEvent::UpdateConfig { admin } => event.add_attribute("admin", admin),
+            TipEvent::Migrate { from_version, to_version } => event
+                .add_attribute("from_version", from_version)
+                .add_attribute("to_version", to_version),
+            TipEvent::FeeShares { shares, admin } => event
+                .add_attribute("shares", shares.join(","))
+                .add_attribute("admin", admin),
+            TipEvent::Pause { operations, sender } | TipEvent::Unpause { operations, sender } => event
+                .add_attribute("operations", operations.join(","))
+                .add_attribute("sender", sender),
+            TipEvent::Block { kind, target, reason, admin } => event
+                .add_attribute("kind", kind)
+                .add_attribute("target", target)
+                .add_attribute("reason", reason.unwrap_or_default())
+                .add_attribute("admin", admin),
+            TipEvent::Unblock { kind, target, admin } => event
+                .add_attribute("kind", kind)
+                .add_attribute("target", target)
+                .add_attribute("admin", admin),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Changing any expectation below breaks downstream indexers: bump
+    // EVENT_SCHEMA_VERSION and update them together.
+    fn attributes(event: TipEvent) -> (String, Vec<(String, String)>) {
+        let event: Event = event.into();
+        let attrs = event.attributes.into_iter().map(|a| (a.key, a.value)).collect();
+        (event.ty, attrs)
+    }
+
+    fn expected(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn schema_version_is_locked() {
+        assert_eq!(EVENT_NAMESPACE, "social_tip");
+        assert_eq!(EVENT_SCHEMA_VERSION, "1");
+    }
+
+    #[test]
+    fn instantiate_schema() {
+        let (ty, attrs) = attributes(TipEvent::Instantiate {
+            admin: "admin".to_string(),
+            token_denom: "uxion".to_string(),
+            platform_wallet: "wallet".to_string(),
+        });
+        assert_eq!(ty, "social_tip.instantiate");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("admin", "admin"),
+            ("token_denom", "uxion"),
+            ("platform_wallet", "wallet"),
+        ]));
+    }
+
+    #[test]
+    fn register_schema() {
+        let (ty, attrs) = attributes(TipEvent::Register {
+            identifier: "bob".to_string(),
+            address: "addr".to_string(),
+            referrer: Some("alice".to_string()),
+        });
+        assert_eq!(ty, "social_tip.register");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("identifier", "bob"),
+            ("address", "addr"),
+            ("referrer", "alice"),
+        ]));
+    }
+
+    #[test]
+    fn referral_reward_schema() {
+        let (ty, attrs) = attributes(TipEvent::ReferralReward {
+            tip_id: 4,
+            referrer: "alice".to_string(),
+            referred: "bob".to_string(),
+            denom: "uxion".to_string(),
+            amount: Uint128::new(2),
+        });
+        assert_eq!(ty, "social_tip.referral_reward");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("tip_id", "4"),
+            ("referrer", "alice"),
+            ("referred", "bob"),
+            ("denom", "uxion"),
+            ("amount", "2"),
+        ]));
+    }
+
+    #[test]
+    fn referral_payout_schema() {
+        let (ty, attrs) = attributes(TipEvent::ReferralPayout {
+            referrer: "alice".to_string(),
+            recipient: "addr".to_string(),
+            denom: "uxion".to_string(),
+            amount: Uint128::new(2),
+        });
+        assert_eq!(ty, "social_tip.referral_payout");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("referrer", "alice"),
+            ("recipient", "addr"),
+            ("denom", "uxion"),
+            ("amount", "2"),
+        ]));
+    }
+
+    #[test]
+    fn profile_schema() {
+        let (ty, attrs) = attributes(TipEvent::Profile {
+            identifier: "bob".to_string(),
+            address: "addr".to_string(),
+            removed: false,
+        });
+        assert_eq!(ty, "social_tip.profile");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("identifier", "bob"),
+            ("address", "addr"),
+            ("removed", "false"),
+        ]));
+    }
+
+    #[test]
+    fn transfer_schema() {
+        let (ty, attrs) = attributes(TipEvent::Transfer {
+            tip_id: 7,
+            sender: "alice".to_string(),
+            identifier: "bob".to_string(),
+            recipient: "addr".to_string(),
+            denom: "uxion".to_string(),
+            gross_amount: Uint128::new(1_000),
+            fee: Uint128::new(10),
+            net_amount: Uint128::new(990),
+            payout: "direct".to_string(),
+        });
+        assert_eq!(ty, "social_tip.transfer");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("tip_id", "7"),
+            ("sender", "alice"),
+            ("identifier", "bob"),
+            ("recipient", "addr"),
+            ("denom", "uxion"),
+            ("gross_amount", "1000"),
+            ("fee", "10"),
+            ("net_amount", "990"),
+            ("payout", "direct"),
+        ]));
+    }
+
+    #[test]
+    fn payout_policy_schema() {
+        let (ty, attrs) = attributes(TipEvent::PayoutPolicy {
+            identifier: "bob".to_string(),
+            policy: "hold".to_string(),
+            owner: "addr".to_string(),
+        });
+        assert_eq!(ty, "social_tip.payout_policy");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("identifier", "bob"),
+            ("policy", "hold"),
+            ("owner", "addr"),
+        ]));
+    }
+
+    #[test]
+    fn deposit_schema() {
+        let (ty, attrs) = attributes(TipEvent::Deposit {
+            address: "addr".to_string(),
+            denom: "uxion".to_string(),
+            amount: Uint128::new(5_000),
+        });
+        assert_eq!(ty, "social_tip.deposit");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("address", "addr"),
+            ("denom", "uxion"),
+            ("amount", "5000"),
+        ]));
+    }
+
+    #[test]
+    fn withdraw_schema() {
+        let (ty, attrs) = attributes(TipEvent::Withdraw {
+            address: "addr".to_string(),
+            denom: "uxion".to_string(),
+            amount: Uint128::new(990),
+        });
+        assert_eq!(ty, "social_tip.withdraw");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("address", "addr"),
+            ("denom", "uxion"),
+            ("amount", "990"),
+        ]));
+    }
+
+    #[test]
+    fn approval_schema() {
+        let (ty, attrs) = attributes(TipEvent::Approval {
+            owner: "alice".to_string(),
+            spender: "bot".to_string(),
+            denom: "uxion".to_string(),
+            amount: Uint128::new(1_000),
+            per_tip_cap: Some(Uint128::new(100)),
+            expires: None,
+            revoked: false,
+        });
+        assert_eq!(ty, "social_tip.approval");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("owner", "alice"),
+            ("spender", "bot"),
+            ("denom", "uxion"),
+            ("amount", "1000"),
+            ("per_tip_cap", "100"),
+            ("expires", ""),
+            ("revoked", "false"),
+        ]));
+    }
+
+    #[test]
+    fn transfer_from_schema() {
+        let (ty, attrs) = attributes(TipEvent::TransferFrom {
+            owner: "alice".to_string(),
+            spender: "bot".to_string(),
+            denom: "uxion".to_string(),
+            amount: Uint128::new(100),
+            remaining: Uint128::new(900),
+        });
+        assert_eq!(ty, "social_tip.transfer_from");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("owner", "alice"),
+            ("spender", "bot"),
+            ("denom", "uxion"),
+            ("amount", "100"),
+            ("remaining", "900"),
+        ]));
+    }
+
+    #[test]
+    fn micro_tip_schema() {
+        let (ty, attrs) = attributes(TipEvent::MicroTip {
+            tip_id: 9,
+            sender: "alice".to_string(),
+            identifier: "bob".to_string(),
+            denom: "uxion".to_string(),
+            amount: Uint128::new(50),
+            pending_amount: Uint128::new(150),
+        });
+        assert_eq!(ty, "social_tip.micro_tip");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("tip_id", "9"),
+            ("sender", "alice"),
+            ("identifier", "bob"),
+            ("denom", "uxion"),
+            ("amount", "50"),
+            ("pending_amount", "150"),
+        ]));
+    }
+
+    #[test]
+    fn settle_schema() {
+        let (ty, attrs) = attributes(TipEvent::Settle {
+            identifier: "bob".to_string(),
+            recipient: "addr".to_string(),
+            denom: "uxion".to_string(),
+            tips: 3,
+            gross_amount: Uint128::new(150),
+            fee: Uint128::new(1),
+            net_amount: Uint128::new(149),
+            payout: "direct".to_string(),
+        });
+        assert_eq!(ty, "social_tip.settle");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("identifier", "bob"),
+            ("recipient", "addr"),
+            ("denom", "uxion"),
+            ("tips", "3"),
+            ("gross_amount", "150"),
+            ("fee", "1"),
+            ("net_amount", "149"),
+            ("payout", "direct"),
+        ]));
+    }
+
+    #[test]
+    fn escrow_schema() {
+        let (ty, attrs) = attributes(TipEvent::Escrow {
+            tip_id: 8,
+            sender: "alice".to_string(),
+            identifier: "bob@mail.com".to_string(),
+            denom: "uxion".to_string(),
+            gross_amount: Uint128::new(1_000),
+            fee: Uint128::new(10),
+            net_amount: Uint128::new(990),
+            claim_id: "c1a1".to_string(),
+            commitment: "abcd".to_string(),
+            expires_at: Timestamp::from_seconds(1_700_000_000),
+        });
+        assert_eq!(ty, "social_tip.escrow");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("tip_id", "8"),
+            ("sender", "alice"),
+            ("identifier", "bob@mail.com"),
+            ("denom", "uxion"),
+            ("gross_amount", "1000"),
+            ("fee", "10"),
+            ("net_amount", "990"),
+            ("claim_id", "c1a1"),
+            ("commitment", "abcd"),
+            ("expires_at", "1700000000"),
+        ]));
+    }
+
+    #[test]
+    fn claim_commit_schema() {
+        let (ty, attrs) = attributes(TipEvent::ClaimCommit {
+            escrow_id: 8,
+            committer: "addr".to_string(),
+        });
+        assert_eq!(ty, "social_tip.claim_commit");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("escrow_id", "8"),
+            ("committer", "addr"),
+        ]));
+    }
+
+    #[test]
+    fn claim_schema() {
+        let (ty, attrs) = attributes(TipEvent::Claim {
+            tip_id: 8,
+            identifier: "bob@mail.com".to_string(),
+            recipient: "addr".to_string(),
+            denom: "uxion".to_string(),
+            net_amount: Uint128::new(990),
+            remaining_amount: Uint128::zero(),
+        });
+        assert_eq!(ty, "social_tip.claim");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("tip_id", "8"),
+            ("identifier", "bob@mail.com"),
+            ("recipient", "addr"),
+            ("denom", "uxion"),
+            ("net_amount", "990"),
+            ("remaining_amount", "0"),
+        ]));
+    }
+
+    #[test]
+    fn refund_schema() {
+        let (ty, attrs) = attributes(TipEvent::Refund {
+            tip_id: 8,
+            identifier: "bob@mail.com".to_string(),
+            sender: "alice".to_string(),
+            denom: "uxion".to_string(),
+            net_amount: Uint128::new(990),
+            forced: true,
+        });
+        assert_eq!(ty, "social_tip.refund");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("tip_id", "8"),
+            ("identifier", "bob@mail.com"),
+            ("sender", "alice"),
+            ("denom", "uxion"),
+            ("net_amount", "990"),
+            ("forced", "true"),
+        ]));
+    }
+
+    #[test]
+    fn relay_schema() {
+        let (ty, attrs) = attributes(TipEvent::Relay {
+            identifier: "bob".to_string(),
+            signer: "addr".to_string(),
+            relayer: "relayer".to_string(),
+            nonce: 0,
+            denom: "uxion".to_string(),
+            relayer_fee: Uint128::new(5),
+        });
+        assert_eq!(ty, "social_tip.relay");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("identifier", "bob"),
+            ("signer", "addr"),
+            ("relayer", "relayer"),
+            ("nonce", "0"),
+            ("denom", "uxion"),
+            ("relayer_fee", "5"),
+        ]));
+    }
+
+    #[test]
+    fn fee_withdrawal_schema() {
+        let (ty, attrs) = attributes(TipEvent::FeeWithdrawal {
+            recipient: "treasury".to_string(),
+            denom: "uxion".to_string(),
+            amount: Uint128::new(70),
+            bps: 7_000,
+        });
+        assert_eq!(ty, "social_tip.fee_withdrawal");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("recipient", "treasury"),
+            ("denom", "uxion"),
+            ("amount", "70"),
+            ("bps", "7000"),
+        ]));
+    }
+
+    #[test]
+    fn fee_shares_schema() {
+        let (ty, attrs) = attributes(TipEvent::FeeShares {
+            shares: vec!["treasury:7000".to_string(), "fund:3000".to_string()],
+            admin: "admin".to_string(),
+        });
+        assert_eq!(ty, "social_tip.fee_shares");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("shares", "treasury:7000,fund:3000"),
+            ("admin", "admin"),
+        ]));
+    }
+
+    #[test]
+    fn update_config_schema() {
+        let (ty, attrs) = attributes(TipEvent::UpdateConfig { admin: "admin".to_string() });
+        assert_eq!(ty, "social_tip.update_config");
+        assert_eq!(attrs, expected(&[("schema_version", "1"), ("admin", "admin")]));
+    }
+
+    #[test]
+    fn migrate_schema() {
+        let (ty, attrs) = attributes(TipEvent::Migrate {
+            from_version: "0.1.0".to_string(),
+            to_version: "0.2.0".to_string(),
+        });
+        assert_eq!(ty, "social_tip.migrate");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("from_version", "0.1.0"),
+            ("to_version", "0.2.0"),
+        ]));
+    }
+
+    #[test]
+    fn pause_schema() {
+        let operations = vec!["register".to_string(), "transfer".to_string()];
+        let (ty, attrs) = attributes(TipEvent::Pause {
+            operations: operations.clone(),
+            sender: "guardian".to_string(),
+        });
+        assert_eq!(ty, "social_tip.pause");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("operations", "register,transfer"),
+            ("sender", "guardian"),
+        ]));
+        let (ty, _) = attributes(TipEvent::Unpause { operations, sender: "admin".to_string() });
+        assert_eq!(ty, "social_tip.unpause");
+    }
+
+    #[test]
+    fn block_schema() {
+        let (ty, attrs) = attributes(TipEvent::Block {
+            kind: "identifier".to_string(),
+            target: "bob".to_string(),
+            reason: None,
+            admin: "admin".to_string(),
+        });
+        assert_eq!(ty, "social_tip.block");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("kind", "identifier"),
+            ("target", "bob"),
+            ("reason", ""),
+            ("admin", "admin"),
+        ]));
+        let (ty, attrs) = attributes(TipEvent::Unblock {
+            kind: "address".to_string(),
+            target: "addr".to_string(),
+            admin: "admin".to_string(),
+        });
+        assert_eq!(ty, "social_tip.unblock");
+        assert_eq!(attrs, expected(&[
+            ("schema_version", "1"),
+            ("kind", "address"),
+            ("target", "addr"),
+            ("admin", "admin"),
+        ]));
+    }
+}
@@ -0,0 +1,151 @@
+use cosmwasm_std::{Api, Coin, Order, StdError, StdResult, Storage, Timestamp, Uint128};
+
+use crate::state::{FeeShare, ReferralPolicy, FEE_POOL, REFERRALS, REFERRAL_EARNINGS};
+
+// fee shares are given in basis points and must add up to 100%
+pub const TOTAL_BPS: u16 = 10_000;
+
+// platform fee charged on every tip, or on every settled micro-tip batch
+pub const PLATFORM_FEE_BPS: u16 = 100;
+
+// rounds down, so amounts under 100 units pay no fee on their own
+pub fn platform_fee(amount: Uint128) -> Uint128 {
+    amount.multiply_ratio(PLATFORM_FEE_BPS, TOTAL_BPS)
+}
+
+// shares must name valid, distinct wallets and add up to exactly TOTAL_BPS
+pub fn validate_fee_shares(api: &dyn Api, shares: &[FeeShare]) -> StdResult<()> {
+    let mut total: u32 = 0;
+    for (i, share) in shares.iter().enumerate() {
+        api.addr_validate(share.address.as_str())?;
+        if share.bps == 0 {
+            return Err(StdError::generic_err(format!("Fee share for {} is zero", share.address)));
+        }
+        if shares[..i].iter().any(|s| s.address == share.address) {
+            return Err(StdError::generic_err(format!("Duplicate fee wallet {}", share.address)));
+        }
+        total += u32::from(share.bps);
+    }
+    if total != u32::from(TOTAL_BPS) {
+        return Err(StdError::generic_err(format!("Fee shares must add up to {} bps", TOTAL_BPS)));
+    }
+    Ok(())
+}
+
+// hold a transfer fee in the pool until the next WithdrawFees
+pub fn accrue(storage: &mut dyn Storage, fee: &Coin) -> StdResult<()> {
+    if fee.amount.is_zero() {
+        return Ok(());
+    }
+    FEE_POOL.update(storage, fee.denom.clone(), |pool| -> StdResult<_> {
+        Ok(pool.unwrap_or_default() + fee.amount)
+    })?;
+    Ok(())
+}
+
+pub fn validate_referral_policy(policy: &ReferralPolicy) -> StdResult<()> {
+    if policy.bps > TOTAL_BPS {
+        return Err(StdError::generic_err(format!("Referral share cannot exceed {} bps", TOTAL_BPS)));
+    }
+    Ok(())
+}
+
+// Collect the fee of a tip to a registered identifier. While the identifier's
+// referral is active the referrer is credited their cut and only the rest goes
+// to the pool; the tipper pays the same fee either way. Returns the referrer
+// and the amount credited, if any.
+pub fn collect_fee(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    policy: &ReferralPolicy,
+    identifier: &str,
+    fee: &Coin,
+) -> StdResult<Option<(String, Uint128)>> {
+    let referral = match REFERRALS.may_load(storage, identifier.to_string())? {
+        Some(referral) if policy.bps > 0 && now < referral.since.plus_seconds(policy.duration) => referral,
+        _ => {
+            accrue(storage, fee)?;
+            return Ok(None);
+        }
+    };
+    let cut = fee.amount.multiply_ratio(policy.bps, TOTAL_BPS);
+    accrue(storage, &Coin::new(fee.amount - cut, &fee.denom))?;
+    if cut.is_zero() {
+        return Ok(None);
+    }
+    let key = (referral.referrer.clone(), fee.denom.clone());
+    let mut earnings = REFERRAL_EARNINGS.may_load(storage, key.clone())?.unwrap_or_default();
+    earnings.earned += cut;
+    earnings.unpaid += cut;
+    REFERRAL_EARNINGS.save(storage, key, &earnings)?;
+    Ok(Some((referral.referrer, cut)))
+}
+
+pub fn accrued(storage: &dyn Storage) -> StdResult<Vec<Coin>> {
+    FEE_POOL
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin::new(amount, denom)))
+        .collect()
+}
+
+// Empty the pool and split it by share. Rounding dust goes to the first
+// wallet so the pool is always fully paid out.
+pub fn drain(storage: &mut dyn Storage, shares: &[FeeShare]) -> StdResult<Vec<(FeeShare, Coin)>> {
+    let mut payouts = vec![];
+    for pool in accrued(storage)? {
+        FEE_POOL.remove(storage, pool.denom.clone());
+        let cuts: Vec<Uint128> = shares
+            .iter()
+            .map(|share| pool.amount.multiply_ratio(share.bps, TOTAL_BPS))
+            .collect();
+        let dust = pool.amount - cuts.iter().copied().sum::<Uint128>();
+        for (i, (share, cut)) in shares.iter().zip(cuts).enumerate() {
+            let cut = if i == 0 { cut + dust } else { cut };
+            if !cut.is_zero() {
+                payouts.push((share.clone(), Coin::new(cut, &pool.denom)));
+            }
+        }
+    }
+    Ok(payouts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{MockApi, MockStorage};
+
+    fn shares(api: &MockApi, bps: &[u16]) -> Vec<FeeShare> {
+        bps.iter()
+            .enumerate()
+            .map(|(i, bps)| FeeShare { address: api.addr_make(&format!("wallet{}", i)), bps: *bps })
+            .collect()
+    }
+
+    #[test]
+    fn shares_must_cover_the_whole_fee() {
+        let api = MockApi::default();
+        assert!(validate_fee_shares(&api, &shares(&api, &[7_000, 3_000])).is_ok());
+        assert!(validate_fee_shares(&api, &shares(&api, &[7_000, 2_000])).is_err());
+        assert!(validate_fee_shares(&api, &shares(&api, &[10_000, 0])).is_err());
+        assert!(validate_fee_shares(&api, &[]).is_err());
+
+        let mut twice = shares(&api, &[5_000]);
+        twice.push(twice[0].clone());
+        assert!(validate_fee_shares(&api, &twice).is_err());
+    }
+
+    #[test]
+    fn drain_pays_out_everything() {
+        let api = MockApi::default();
+        let mut storage = MockStorage::new();
+        let shares = shares(&api, &[3_333, 3_333, 3_334]);
+        accrue(&mut storage, &Coin::new(100u128, "uxion")).unwrap();
+        accrue(&mut storage, &Coin::new(1u128, "uxion")).unwrap();
+
+        let payouts = drain(&mut storage, &shares).unwrap();
+        let amounts: Vec<u128> = payouts.iter().map(|(_, coin)| coin.amount.u128()).collect();
+        // 101 splits into 33/33/33 with the 2 left over going to the first wallet
+        assert_eq!(amounts, vec![35, 33, 33]);
+        assert!(accrued(&storage).unwrap().is_empty());
+    }
+}
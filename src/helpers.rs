@@ -1,5 +1,23 @@
-use cosmwasm_std::{Response, StdError, StdResult};
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Coin, CosmosMsg, QuerierWrapper, Response, StdError, StdResult, Timestamp, Uint128,
+    WasmMsg,
+};
 use regex::Regex;   
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::events::TipEvent;
+use crate::msg::{
+    AccountResponse, AllowancesResponse, BalanceResponse, BlockTarget, BlockedResponse, ClaimPayload, ConfigUpdate,
+    EscrowResponse, EscrowsResponse, ExecuteMsg, FeePoolResponse, FeeShareMsg, InternalBalanceResponse,
+    LeaderboardResponse, MicroTipsResponse, PauseResponse, PayoutPolicyMsg, PayoutPolicyResponse, ProfileResponse,
+    QueryMsg, ReferralEarningsResponse, ReferralResponse, RelayNonceResponse, SignatureScheme, StatsResponse,
+    TipResponse, TipsResponse,
+};
+use crate::snapshot::{SnapshotHeader, SnapshotPage, SnapshotSection};
+use crate::state::{Config, Operation, Profile};
 
 
 // validate email format
@@ -31,12 +49,481 @@ pub fn validate_identifier(identifier: String) -> StdResult<()> {
     }
 }
 
-// standardize response creation 
-pub fn create_response(action: &str, attributes: Vec<(&str, &str)>) -> Response {
-    let mut response = Response::new().add_attribute("action", action);
-    for (key, value) in attributes {
-        response = response.add_attribute(key, value);
+// Cosmos SDK denom format: a letter followed by 2-127 letters, digits or
+// `/:._-`, which covers native, ibc/ and factory/ denoms
+pub fn validate_denom(denom: &str) -> StdResult<()> {
+    if denom.is_empty() {
+        return Err(StdError::generic_err("Denom must not be empty"));
+    }
+    let denom_regex = Regex::new(r"^[a-zA-Z][a-zA-Z0-9/:._-]{2,127}$").unwrap();
+    if !denom_regex.is_match(denom) {
+        return Err(StdError::generic_err(format!("Invalid denom `{}`", denom)));
+    }
+    Ok(())
+}
+
+// memos are free text but kept short
+pub const MAX_MEMO_LEN: usize = 256;
+
+pub fn validate_memo(memo: &str) -> StdResult<()> {
+    if memo.len() > MAX_MEMO_LEN {
+        return Err(StdError::generic_err(format!("Memo exceeds {} bytes", MAX_MEMO_LEN)));
+    }
+    Ok(())
+}
+
+// profile size limits, in bytes
+pub const MAX_DISPLAY_NAME_LEN: usize = 64;
+pub const MAX_BIO_LEN: usize = 512;
+pub const MAX_URI_LEN: usize = 256;
+pub const MAX_PROFILE_LINKS: usize = 5;
+
+pub fn validate_profile(profile: &Profile) -> StdResult<()> {
+    if let Some(name) = &profile.display_name {
+        validate_text("Display name", name, MAX_DISPLAY_NAME_LEN)?;
+        if name.trim().is_empty() {
+            return Err(StdError::generic_err("Display name must not be blank"));
+        }
+    }
+    if let Some(bio) = &profile.bio {
+        validate_text("Bio", bio, MAX_BIO_LEN)?;
+    }
+    if let Some(avatar) = &profile.avatar_uri {
+        validate_uri(avatar, &["https://", "ipfs://"])?;
+    }
+    if profile.links.len() > MAX_PROFILE_LINKS {
+        return Err(StdError::generic_err(format!("At most {} profile links", MAX_PROFILE_LINKS)));
+    }
+    for link in &profile.links {
+        validate_uri(link, &["https://"])?;
+    }
+    if let Some(denom) = &profile.payout_denom {
+        validate_denom(denom)?;
+    }
+    Ok(())
+}
+
+// free text shown on tip pages: bounded and without control characters,
+// newlines excepted
+fn validate_text(field: &str, text: &str, max_len: usize) -> StdResult<()> {
+    if text.len() > max_len {
+        return Err(StdError::generic_err(format!("{} exceeds {} bytes", field, max_len)));
+    }
+    if text.chars().any(|c| c.is_control() && c != '\n') {
+        return Err(StdError::generic_err(format!("{} contains control characters", field)));
     }
-    response
+    Ok(())
+}
+
+fn validate_uri(uri: &str, schemes: &[&str]) -> StdResult<()> {
+    if uri.len() > MAX_URI_LEN {
+        return Err(StdError::generic_err(format!("URI exceeds {} bytes", MAX_URI_LEN)));
+    }
+    let rest = schemes.iter().find_map(|scheme| uri.strip_prefix(scheme));
+    match rest {
+        Some(rest) if !rest.is_empty() && !rest.chars().any(|c| c.is_whitespace() || c.is_control()) => Ok(()),
+        _ => Err(StdError::generic_err(format!("URI must start with {}", schemes.join(" or ")))),
+    }
+}
+
+// deterministic claim id for an escrowed tip, unique per contract and tip
+pub fn claim_id(contract: &Addr, tip_id: u64) -> String {
+    let hash = Sha256::digest(format!("{}:{}", contract, tip_id));
+    hex::encode(&hash[..16])
+}
+
+// hash binding a claim link to what it pays out. It is not keyed, so it only
+// catches links garbled on the way; the mailer checks the escrow on chain.
+pub fn claim_commitment(claim_id: &str, identifier: &str, amount: &Coin, expires_at: Timestamp) -> String {
+    let preimage = format!(
+        "social_tip:v1|{}|{}|{}|{}|{}",
+        claim_id,
+        identifier,
+        amount.denom,
+        amount.amount,
+        expires_at.seconds()
+    );
+    hex::encode(Sha256::digest(preimage))
+}
+
+// hex sha256 of a claim link secret, as stored on the escrow
+pub fn secret_hash(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+// hex sha256 committed with CommitClaim before the secret is revealed. It
+// binds the secret to the recipient, so whoever copies the reveal from the
+// mempool cannot redirect the claim to themselves.
+pub fn claim_reveal_hash(secret: &str, recipient: &Addr) -> String {
+    hex::encode(Sha256::digest(format!("{}|{}", secret, recipient)))
+}
+
+pub fn validate_secret_hash(hash: &str) -> StdResult<()> {
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(StdError::generic_err("Secret hash must be a hex encoded sha256"));
+    }
+    Ok(())
+}
+
+// standardize response creation: the `action` attribute plus the typed event
+pub fn create_response(event: TipEvent) -> Response {
+    Response::new()
+        .add_attribute("action", event.action())
+        .add_event(event)
+}
+
+// same as create_response for actions touching several tips, one event each
+pub fn create_batch_response(action: &str, events: Vec<TipEvent>) -> Response {
+    Response::new()
+        .add_attribute("action", action)
+        .add_events(events)
 }
 
+// Typed client for other contracts: builds the CosmosMsg for every execute
+// message, with funds attached where the contract expects them, and runs
+// queries through a QuerierWrapper.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SocialTipContract(pub Addr);
+
+impl SocialTipContract {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    pub fn call(&self, msg: ExecuteMsg, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg: to_json_binary(&msg)?,
+            funds,
+        }
+        .into())
+    }
+
+    pub fn register(
+        &self,
+        identifier: impl Into<String>,
+        claim_pending: bool,
+        referrer: Option<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Register { identifier: identifier.into(), claim_pending, referrer }, vec![])
+    }
+
+    // tip paid with attached funds, which must be exactly `amount`
+    pub fn transfer(
+        &self,
+        identifier: impl Into<String>,
+        amount: Coin,
+        memo: Option<String>,
+        secret_hash: Option<String>,
+    ) -> StdResult<CosmosMsg> {
+        let funds = vec![amount.clone()];
+        self.call(
+            ExecuteMsg::Transfer { identifier: identifier.into(), amount, memo, secret_hash, from_balance: false },
+            funds,
+        )
+    }
+
+    // tip paid from the sender's internal balance, nothing attached
+    pub fn transfer_from_balance(
+        &self,
+        identifier: impl Into<String>,
+        amount: Coin,
+        memo: Option<String>,
+        secret_hash: Option<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::Transfer { identifier: identifier.into(), amount, memo, secret_hash, from_balance: true },
+            vec![],
+        )
+    }
+
+    pub fn deposit(&self, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Deposit {}, funds)
+    }
+
+    pub fn settle(&self, limit: Option<u32>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Settle { limit }, vec![])
+    }
+
+    pub fn claim(
+        &self,
+        identifier: impl Into<String>,
+        destination: Option<String>,
+        denom: Option<String>,
+        amount: Option<Uint128>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Claim { identifier: identifier.into(), destination, denom, amount }, vec![])
+    }
+
+    pub fn commit_claim(&self, escrow_id: u64, commitment: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::CommitClaim { escrow_id, commitment: commitment.into() }, vec![])
+    }
+
+    pub fn claim_with_secret(
+        &self,
+        escrow_id: u64,
+        secret: impl Into<String>,
+        recipient: Option<String>,
+        register: bool,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ClaimWithSecret { escrow_id, secret: secret.into(), recipient, register }, vec![])
+    }
+
+    pub fn refund(&self, identifier: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Refund { identifier: identifier.into() }, vec![])
+    }
+
+    pub fn relay_claim(
+        &self,
+        payload: ClaimPayload,
+        scheme: SignatureScheme,
+        pubkey: Binary,
+        signature: Binary,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RelayClaim { payload, scheme, pubkey, signature }, vec![])
+    }
+
+    pub fn withdraw_fees(&self) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::WithdrawFees {}, vec![])
+    }
+
+    pub fn set_profile(&self, identifier: impl Into<String>, profile: Profile) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SetProfile { identifier: identifier.into(), profile }, vec![])
+    }
+
+    pub fn remove_profile(&self, identifier: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RemoveProfile { identifier: identifier.into() }, vec![])
+    }
+
+    pub fn set_payout_policy(&self, identifier: impl Into<String>, policy: PayoutPolicyMsg) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SetPayoutPolicy { identifier: identifier.into(), policy }, vec![])
+    }
+
+    pub fn approve(
+        &self,
+        spender: impl Into<String>,
+        denom: impl Into<String>,
+        amount: Uint128,
+        per_tip_cap: Option<Uint128>,
+        expires: Option<Timestamp>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::Approve { spender: spender.into(), denom: denom.into(), amount, per_tip_cap, expires },
+            vec![],
+        )
+    }
+
+    pub fn revoke(&self, spender: impl Into<String>, denom: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Revoke { spender: spender.into(), denom: denom.into() }, vec![])
+    }
+
+    pub fn transfer_from(
+        &self,
+        owner: impl Into<String>,
+        identifier: impl Into<String>,
+        amount: Coin,
+        memo: Option<String>,
+        secret_hash: Option<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::TransferFrom { owner: owner.into(), identifier: identifier.into(), amount, memo, secret_hash },
+            vec![],
+        )
+    }
+
+    pub fn withdraw(&self, denom: impl Into<String>, amount: Option<Uint128>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Withdraw { denom: denom.into(), amount }, vec![])
+    }
+
+    pub fn claim_referral_rewards(&self, identifier: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ClaimReferralRewards { identifier: identifier.into() }, vec![])
+    }
+
+    pub fn set_fee_shares(&self, shares: Vec<FeeShareMsg>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SetFeeShares { shares }, vec![])
+    }
+
+    pub fn update_config(&self, update: ConfigUpdate) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::UpdateConfig(update), vec![])
+    }
+
+    pub fn pause(&self, operations: Vec<Operation>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Pause { operations }, vec![])
+    }
+
+    pub fn unpause(&self, operations: Vec<Operation>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Unpause { operations }, vec![])
+    }
+
+    pub fn block(&self, target: BlockTarget, reason: Option<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Block { target, reason }, vec![])
+    }
+
+    pub fn unblock(&self, target: BlockTarget) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Unblock { target }, vec![])
+    }
+
+    pub fn force_refund(&self, identifier: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ForceRefund { identifier: identifier.into() }, vec![])
+    }
+
+    pub fn query<T: DeserializeOwned>(&self, querier: &QuerierWrapper, msg: QueryMsg) -> StdResult<T> {
+        querier.query_wasm_smart(self.addr(), &msg)
+    }
+
+    pub fn balance(&self, querier: &QuerierWrapper, address: impl Into<String>) -> StdResult<BalanceResponse> {
+        self.query(querier, QueryMsg::GetBalance { address: address.into() })
+    }
+
+    pub fn escrow(&self, querier: &QuerierWrapper, identifier: impl Into<String>) -> StdResult<EscrowResponse> {
+        self.query(querier, QueryMsg::GetEscrow { identifier: identifier.into() })
+    }
+
+    pub fn escrows(
+        &self,
+        querier: &QuerierWrapper,
+        identifier: impl Into<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<EscrowsResponse> {
+        self.query(querier, QueryMsg::GetEscrows { identifier: identifier.into(), start_after, limit })
+    }
+
+    pub fn escrow_by_id(&self, querier: &QuerierWrapper, escrow_id: u64) -> StdResult<EscrowResponse> {
+        self.query(querier, QueryMsg::GetEscrowById { escrow_id })
+    }
+
+    pub fn account(&self, querier: &QuerierWrapper, identifier: impl Into<String>) -> StdResult<AccountResponse> {
+        self.query(querier, QueryMsg::GetAccount { identifier: identifier.into() })
+    }
+
+    pub fn tip(&self, querier: &QuerierWrapper, id: u64) -> StdResult<TipResponse> {
+        self.query(querier, QueryMsg::GetTip { id })
+    }
+
+    pub fn tips_sent(
+        &self,
+        querier: &QuerierWrapper,
+        sender: impl Into<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<TipsResponse> {
+        self.query(querier, QueryMsg::TipsSent { sender: sender.into(), start_after, limit })
+    }
+
+    pub fn tips_received(
+        &self,
+        querier: &QuerierWrapper,
+        identifier: impl Into<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<TipsResponse> {
+        self.query(querier, QueryMsg::TipsReceived { identifier: identifier.into(), start_after, limit })
+    }
+
+    pub fn relay_nonce(&self, querier: &QuerierWrapper, address: impl Into<String>) -> StdResult<RelayNonceResponse> {
+        self.query(querier, QueryMsg::RelayNonce { address: address.into() })
+    }
+
+    pub fn referral(&self, querier: &QuerierWrapper, identifier: impl Into<String>) -> StdResult<ReferralResponse> {
+        self.query(querier, QueryMsg::GetReferral { identifier: identifier.into() })
+    }
+
+    pub fn referral_earnings(
+        &self,
+        querier: &QuerierWrapper,
+        identifier: impl Into<String>,
+    ) -> StdResult<ReferralEarningsResponse> {
+        self.query(querier, QueryMsg::ReferralEarnings { identifier: identifier.into() })
+    }
+
+    pub fn payout_policy(
+        &self,
+        querier: &QuerierWrapper,
+        identifier: impl Into<String>,
+    ) -> StdResult<PayoutPolicyResponse> {
+        self.query(querier, QueryMsg::PayoutPolicy { identifier: identifier.into() })
+    }
+
+    pub fn pending_micro_tips(
+        &self,
+        querier: &QuerierWrapper,
+        identifier: impl Into<String>,
+    ) -> StdResult<MicroTipsResponse> {
+        self.query(querier, QueryMsg::PendingMicroTips { identifier: identifier.into() })
+    }
+
+    pub fn internal_balance(
+        &self,
+        querier: &QuerierWrapper,
+        address: impl Into<String>,
+    ) -> StdResult<InternalBalanceResponse> {
+        self.query(querier, QueryMsg::InternalBalance { address: address.into() })
+    }
+
+    pub fn allowances(
+        &self,
+        querier: &QuerierWrapper,
+        owner: impl Into<String>,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    ) -> StdResult<AllowancesResponse> {
+        self.query(querier, QueryMsg::Allowances { owner: owner.into(), start_after, limit })
+    }
+
+    pub fn profile(&self, querier: &QuerierWrapper, identifier: impl Into<String>) -> StdResult<ProfileResponse> {
+        self.query(querier, QueryMsg::GetProfile { identifier: identifier.into() })
+    }
+
+    pub fn config(&self, querier: &QuerierWrapper) -> StdResult<Config> {
+        self.query(querier, QueryMsg::Config {})
+    }
+
+    pub fn snapshot(&self, querier: &QuerierWrapper) -> StdResult<SnapshotHeader> {
+        self.query(querier, QueryMsg::Snapshot {})
+    }
+
+    pub fn snapshot_page(
+        &self,
+        querier: &QuerierWrapper,
+        section: SnapshotSection,
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    ) -> StdResult<SnapshotPage> {
+        self.query(querier, QueryMsg::SnapshotPage { section, start_after, limit })
+    }
+
+    pub fn fee_pool(&self, querier: &QuerierWrapper) -> StdResult<FeePoolResponse> {
+        self.query(querier, QueryMsg::FeePool {})
+    }
+
+    pub fn pause_status(&self, querier: &QuerierWrapper) -> StdResult<PauseResponse> {
+        self.query(querier, QueryMsg::PauseStatus {})
+    }
+
+    pub fn is_blocked(&self, querier: &QuerierWrapper, target: BlockTarget) -> StdResult<BlockedResponse> {
+        self.query(querier, QueryMsg::IsBlocked { target })
+    }
+
+    pub fn stats(&self, querier: &QuerierWrapper, denom: impl Into<String>) -> StdResult<StatsResponse> {
+        self.query(querier, QueryMsg::Stats { denom: denom.into() })
+    }
+
+    pub fn top_recipients(
+        &self,
+        querier: &QuerierWrapper,
+        denom: impl Into<String>,
+        limit: Option<u32>,
+    ) -> StdResult<LeaderboardResponse> {
+        self.query(querier, QueryMsg::TopRecipients { denom: denom.into(), limit })
+    }
+
+    pub fn top_senders(
+        &self,
+        querier: &QuerierWrapper,
+        denom: impl Into<String>,
+        limit: Option<u32>,
+    ) -> StdResult<LeaderboardResponse> {
+        self.query(querier, QueryMsg::TopSenders { denom: denom.into(), limit })
+    }
+}
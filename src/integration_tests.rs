@@ -3,22 +3,38 @@
 mod integration_tests {
 
     use cosmwasm_std::{
-        testing::{message_info, mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage}, Addr, BankMsg, Coin, CosmosMsg, OwnedDeps, Response, StdError, StdResult, Uint128
+        testing::{message_info, mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage}, BankMsg, Coin, CosmosMsg, OwnedDeps, Response, StdError, Uint128
     };
-    use crate::contract::{execute, instantiate, query};
+    use crate::contract::{execute, instantiate, migrate, query, sudo};
+    use crate::helpers::{claim_reveal_hash, secret_hash};
+    use crate::ContractError;
     use crate::msg::{
-        ExecuteMsg, InstantiateMsg, QueryMsg, EscrowResponse, AccountResponse,
+        ExecuteMsg, InstantiateMsg, QueryMsg, EscrowResponse, AccountResponse, TipsResponse,
+        LeaderboardEntry, LeaderboardResponse, PauseResponse, StatsResponse, BlockTarget, BlockedResponse, EscrowsResponse,
+        AllowanceInfo, AllowancesResponse, ClaimPayload, FeePoolResponse, SudoMsg, MicroTipsResponse, PendingMicroTips, FeeShareMsg, ConfigUpdate, MigrateMsg, ProfileResponse, PayoutPolicyMsg, PayoutPolicyResponse, InternalBalanceResponse, RelayNonceResponse, SignatureScheme, ReferralEarning, ReferralEarningsResponse,
+        ReferralResponse,
     };
+    use crate::relay::{pubkey_to_addr, sign_bytes};
+    use crate::state::{Config, FeeShare, Operation, PayoutPolicy, Profile, PauseState, Referral, ReferralPolicy, RateLimits, RetentionPolicy, TipStatus};
     
 
 
     fn setup_contract(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
     ) -> (Response, String) {
         let env = mock_env();
-        let info = message_info(&Addr::unchecked("creator"), &[]);
+        let info = message_info(&deps.api.addr_make("creator"), &[]);
         let msg = InstantiateMsg {
+            admin: None,
+            guardian: None,
             token_denom: "uxion".to_string(),
-            platform_wallet: "platform_wallet".to_string(),
+            platform_wallet: deps.api.addr_make("platform_wallet").to_string(),
+            fee_shares: vec![],
+            tip_retention: RetentionPolicy::default(),
+            rate_limits: RateLimits::default(),
+            claim_link_ttl: None,
+            max_relayer_fee: None,
+            referral: ReferralPolicy::default(),
+            micro_tip_threshold: None,
         };
         let res = instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
         let contract_addr = env.contract.address.to_string();
@@ -26,15 +42,24 @@ mod integration_tests {
     }
 
     #[test]
-    fn test_full_workflow() -> StdResult<()> {
+    fn test_full_workflow() -> Result<(), ContractError> {
         let mut deps = mock_dependencies();
         let env = mock_env();
-        let sender = message_info(&Addr::unchecked("sender"), &[]);
-        let recipient = message_info(&Addr::unchecked("recipient"), &[]);
+        let sender = message_info(&deps.api.addr_make("sender"), &[]);
+        let recipient = message_info(&deps.api.addr_make("recipient"), &[]);
 
         let instantiate_msg = InstantiateMsg {
+            admin: None,
+            guardian: None,
             token_denom: "uxion".to_string(),
-            platform_wallet: "platform_wallet".to_string(),
+            platform_wallet: deps.api.addr_make("platform_wallet").to_string(),
+            fee_shares: vec![],
+            tip_retention: RetentionPolicy::default(),
+            rate_limits: RateLimits::default(),
+            claim_link_ttl: None,
+            max_relayer_fee: None,
+            referral: ReferralPolicy::default(),
+            micro_tip_threshold: None,
         };
 
         // Instantiate contract
@@ -43,6 +68,8 @@ mod integration_tests {
         // register sender  with email
         let register_msg = &ExecuteMsg::Register {
             identifier: "sender@mail.com".to_string(),
+            claim_pending: false,
+            referrer: None,
         };
         execute(deps.as_mut(), env.clone(), sender.clone(), register_msg.clone())?;
 
@@ -52,7 +79,7 @@ mod integration_tests {
         };
         let res = query(deps.as_ref(), env.clone(), query_msg)?;
         let account: AccountResponse = cosmwasm_std::from_json(&res)?;
-        assert_eq!(account.address, Some(Addr::unchecked("sender")));
+        assert_eq!(account.address, Some(deps.api.addr_make("sender")));
 
         // transfer to unregistered email (should go to escrow)
         let full_amount = Uint128::from(10_000_000u128);
@@ -65,8 +92,11 @@ mod integration_tests {
                 denom: "uxion".to_string(),
                 amount: full_amount,
             },
+            memo: None,
+            secret_hash: None,
+            from_balance: false,
         };
-        let sender_with_balance = message_info(&Addr::unchecked("sender"), &[Coin {
+        let sender_with_balance = message_info(&deps.api.addr_make("sender"), &[Coin {
             denom: "uxion".to_string(),
             amount: full_amount,
         }]);
@@ -75,20 +105,12 @@ mod integration_tests {
         // check that escrow was created
         assert_eq!(res.attributes[0].value, "escrow");
 
-        // check that platform fee was sent in a BankMsg
-        let fee_msg = res.messages.iter().find_map(|msg| match &msg.msg{
-            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
-                if to_address == "platform_wallet" {
-                    Some(amount.clone())
-                }else {
-                    None
-                }
-            }
-            _ => None,
-        });
-
+        // the platform fee is held in the pool rather than sent on every tip
+        assert!(res.messages.is_empty());
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::FeePool {})?;
+        let pool: FeePoolResponse = cosmwasm_std::from_json(&res)?;
         assert_eq!(
-            fee_msg.unwrap(),
+            pool.accrued,
             vec![Coin {
                 denom: "uxion".to_string(),
                 amount: platform_fee,
@@ -116,20 +138,25 @@ mod integration_tests {
         // Register recipient with the unregistered email
         let register_recipient_msg = &ExecuteMsg::Register { 
             identifier: "unregistered@mail.com".to_string(), 
+            claim_pending: false,
+            referrer: None,
         };
         execute(deps.as_mut(), env.clone(), recipient.clone(), register_recipient_msg.clone())?;
 
         //Claim escrowed token
         let claim_msg = &ExecuteMsg::Claim { 
             identifier: "unregistered@mail.com".to_string(), 
+            destination: None,
+            denom: None,
+            amount: None,
         };
-        let res = execute(deps.as_mut(), env.clone(), recipient, claim_msg.clone())?;
+        let res = execute(deps.as_mut(), env.clone(), recipient.clone(), claim_msg.clone())?;
         assert_eq!(res.attributes[0].value, "claim");
 
         // verify the claimed amount matches escrow
         let transfer_back = res.messages.iter().find_map(|msg| match &msg.msg {
             CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
-                if to_address == "recipient" {
+                if to_address == recipient.sender.as_str() {
                     Some(amount.clone())
                 }else {
                     None
@@ -162,10 +189,10 @@ mod integration_tests {
     }
 
     #[test]
-    fn test_invalid_email() -> StdResult<()> {
+    fn test_invalid_email() -> Result<(), ContractError> {
         let mut deps = mock_dependencies();
         let env = mock_env();
-        let sender = message_info(&Addr::unchecked("sender"), &[]);
+        let sender = message_info(&deps.api.addr_make("sender"), &[]);
 
         // Instantiate contract
         setup_contract(&mut deps);
@@ -173,12 +200,14 @@ mod integration_tests {
         // Try registering with invalid email
         let register_msg = &ExecuteMsg::Register { 
             identifier: "invalid-email".to_string(), 
+            claim_pending: false,
+            referrer: None,
         };
         let res = execute(deps.as_mut(), env.clone(), sender.clone(), register_msg.clone());
         assert!(res.is_err());
         assert_eq!(
             res.unwrap_err(),
-            StdError::generic_err("Username must be 3-32 alphanumeric characters")
+            ContractError::Std(StdError::generic_err("Username must be 3-32 alphanumeric characters"))
         );
 
         Ok(())  
@@ -186,10 +215,10 @@ mod integration_tests {
     }
 
     #[test]
-    fn test_duplicate_registration() -> StdResult<()> {
+    fn test_duplicate_registration() -> Result<(), ContractError> {
         let mut deps = mock_dependencies();
         let env = mock_env();
-        let sender = message_info(&Addr::unchecked("sender"), &[]);
+        let sender = message_info(&deps.api.addr_make("sender"), &[]);
 
         // Instantiate contract
         setup_contract(&mut deps);
@@ -197,6 +226,8 @@ mod integration_tests {
         // Register email
         let register_msg = &ExecuteMsg::Register { 
             identifier: "user@mail.com".to_string(), 
+            claim_pending: false,
+            referrer: None,
         };
         execute(deps.as_mut(), env.clone(), sender.clone(), register_msg.clone())?;
 
@@ -205,17 +236,17 @@ mod integration_tests {
         assert!(res.is_err());
         assert_eq!(
             res.unwrap_err(),
-            StdError::generic_err("Identifier already registered"),
+            ContractError::Std(StdError::generic_err("Identifier already registered")),
         );
 
         Ok(())
     }
 
     #[test]
-    fn test_transfer_wrong_denom() -> StdResult<()> {
+    fn test_transfer_wrong_denom() -> Result<(), ContractError> {
         let mut deps = mock_dependencies();
         let env = mock_env();
-        let sender = message_info(&Addr::unchecked("sender"), &[Coin {
+        let sender = message_info(&deps.api.addr_make("sender"), &[Coin {
             denom: "wrongdenom".to_string(),
             amount: Uint128::from(10000000u128),
         }]);
@@ -226,8 +257,10 @@ mod integration_tests {
         // Register sender
         let register_msg = &ExecuteMsg::Register { 
             identifier: "sender@mail.com".to_string(), 
+            claim_pending: false,
+            referrer: None,
         };
-        execute(deps.as_mut(), env.clone(), message_info(&Addr::unchecked("sender"), &[]), register_msg.clone())?;
+        execute(deps.as_mut(), env.clone(), message_info(&sender.sender, &[]), register_msg.clone())?;
 
         // Try transfering to wrong denom
         let transfer_msg = &ExecuteMsg::Transfer { 
@@ -236,16 +269,1834 @@ mod integration_tests {
                 denom: "wrongdenom".to_string(),
                 amount: Uint128::from(10000000u128),
             },
+            memo: None,
+            secret_hash: None,
+            from_balance: false,
         };
         let res = execute(deps.as_mut(), env.clone(), sender, transfer_msg.clone());
         assert!(res.is_err());
         assert_eq!(
             res.unwrap_err(),
-            StdError::generic_err("Invalid token denomination"),
+            ContractError::Std(StdError::generic_err("Invalid token denomination")),
+        );
+
+        Ok(())
+
+    }
+
+    fn tip_msg(identifier: &str, amount: u128, memo: Option<&str>) -> ExecuteMsg {
+        ExecuteMsg::Transfer {
+            identifier: identifier.to_string(),
+            amount: Coin {
+                denom: "uxion".to_string(),
+                amount: Uint128::from(amount),
+            },
+            memo: memo.map(|m| m.to_string()),
+            secret_hash: None,
+            from_balance: false,
+        }
+    }
+
+    #[test]
+    fn test_tip_history() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup_contract(&mut deps);
+        let sender = deps.api.addr_make("sender");
+        let recipient = deps.api.addr_make("recipient");
+        let funded = message_info(&sender, &[Coin::new(1_000u128, "uxion")]);
+
+        execute(deps.as_mut(), env.clone(), message_info(&recipient, &[]), ExecuteMsg::Register {
+            identifier: "bob".to_string(),
+            claim_pending: false,
+            referrer: None,
+        })?;
+        execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("bob", 1_000, Some("thanks")))?;
+        execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("carol@mail.com", 1_000, None))?;
+
+        // sent tips come back newest first
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TipsSent {
+            sender: sender.to_string(),
+            start_after: None,
+            limit: None,
+        })?;
+        let sent: TipsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(sent.tips.len(), 2);
+        assert_eq!(sent.tips[0].recipient, "carol@mail.com");
+        assert_eq!(sent.tips[0].status, TipStatus::Escrowed);
+        assert_eq!(sent.tips[1].recipient, "bob");
+        assert_eq!(sent.tips[1].status, TipStatus::Sent);
+        assert_eq!(sent.tips[1].gross, Uint128::from(1_000u128));
+        assert_eq!(sent.tips[1].fee, Uint128::from(10u128));
+        assert_eq!(sent.tips[1].net, Uint128::from(990u128));
+        assert_eq!(sent.tips[1].memo, Some("thanks".to_string()));
+
+        // pagination continues after the given id
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TipsSent {
+            sender: sender.to_string(),
+            start_after: Some(sent.tips[0].id),
+            limit: Some(1),
+        })?;
+        let page: TipsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(page.tips, vec![sent.tips[1].clone()]);
+
+        // claiming the escrow updates the recorded status
+        let carol = deps.api.addr_make("carol");
+        let identifier = "carol@mail.com".to_string();
+        execute(deps.as_mut(), env.clone(), message_info(&carol, &[]), ExecuteMsg::Register { identifier: identifier.clone(), claim_pending: false, referrer: None })?;
+        execute(deps.as_mut(), env.clone(), message_info(&carol, &[]), ExecuteMsg::Claim { identifier: identifier.clone(), destination: None, denom: None, amount: None })?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TipsReceived {
+            identifier,
+            start_after: None,
+            limit: None,
+        })?;
+        let received: TipsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(received.tips.len(), 1);
+        assert_eq!(received.tips[0].status, TipStatus::Claimed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_refund_escrow() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup_contract(&mut deps);
+        let sender = deps.api.addr_make("sender");
+        let other = deps.api.addr_make("other");
+
+        execute(deps.as_mut(), env.clone(), message_info(&sender, &[Coin::new(1_000u128, "uxion")]), tip_msg("dave@mail.com", 1_000, None))?;
+
+        // only the sender may take the escrow back
+        let refund = ExecuteMsg::Refund { identifier: "dave@mail.com".to_string() };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&other, &[]), refund.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Std(StdError::generic_err("Only the escrow sender can refund")));
+
+        let res = execute(deps.as_mut(), env.clone(), message_info(&sender, &[]), refund)?;
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: vec![Coin::new(990u128, "uxion")],
+            })
+        );
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TipsReceived {
+            identifier: "dave@mail.com".to_string(),
+            start_after: None,
+            limit: None,
+        })?;
+        let received: TipsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(received.tips[0].status, TipStatus::Refunded);
+
+        // a sender's escrow is found behind any number of other senders' ones
+        let other_funded = message_info(&other, &[Coin::new(1_000u128, "uxion")]);
+        for _ in 0..31 {
+            execute(deps.as_mut(), env.clone(), other_funded.clone(), tip_msg("erin@mail.com", 1_000, None))?;
+        }
+        execute(deps.as_mut(), env.clone(), message_info(&sender, &[Coin::new(500u128, "uxion")]), tip_msg("erin@mail.com", 500, None))?;
+        let res = execute(deps.as_mut(), env.clone(), message_info(&sender, &[]), ExecuteMsg::Refund {
+            identifier: "erin@mail.com".to_string(),
+        })?;
+        assert_eq!(
+            res.messages,
+            vec![cosmwasm_std::SubMsg::new(BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: vec![Coin::new(495u128, "uxion")],
+            })]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tip_retention() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let sender = deps.api.addr_make("sender");
+        let platform_wallet = deps.api.addr_make("platform_wallet").to_string();
+        instantiate(deps.as_mut(), env.clone(), message_info(&sender, &[]), InstantiateMsg {
+            admin: None,
+            guardian: None,
+            token_denom: "uxion".to_string(),
+            platform_wallet,
+            fee_shares: vec![],
+            tip_retention: RetentionPolicy { max_records: Some(2), max_age: None },
+            rate_limits: RateLimits::default(),
+            claim_link_ttl: None,
+            max_relayer_fee: None,
+            referral: ReferralPolicy::default(),
+            micro_tip_threshold: None,
+        })?;
+        execute(deps.as_mut(), env.clone(), message_info(&sender, &[]), ExecuteMsg::Register {
+            identifier: "bob".to_string(),
+            claim_pending: false,
+            referrer: None,
+        })?;
+
+        let funded = message_info(&sender, &[Coin::new(1_000u128, "uxion")]);
+        for _ in 0..4 {
+            execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("bob", 1_000, None))?;
+        }
+
+        // only the two most recent records are kept
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TipsReceived {
+            identifier: "bob".to_string(),
+            start_after: None,
+            limit: None,
+        })?;
+        let received: TipsResponse = cosmwasm_std::from_json(&res)?;
+        let ids: Vec<u64> = received.tips.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![4, 3]);
+
+        // a run of long-lived escrows at the old end of the ledger does not
+        // stop settled records behind them from being pruned
+        for i in 0..12 {
+            execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg(&format!("user{}@mail.com", i), 1_000, None))?;
+        }
+        for _ in 0..3 {
+            execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("bob", 1_000, None))?;
+        }
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TipsReceived {
+            identifier: "bob".to_string(),
+            start_after: None,
+            limit: None,
+        })?;
+        let received: TipsResponse = cosmwasm_std::from_json(&res)?;
+        let ids: Vec<u64> = received.tips.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![19]);
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TipsSent {
+            sender: sender.to_string(),
+            start_after: None,
+            limit: Some(30),
+        })?;
+        let sent: TipsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(sent.tips.len(), 13);
+        assert!(sent.tips[1..].iter().all(|t| t.status == TipStatus::Escrowed));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_and_leaderboards() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup_contract(&mut deps);
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+
+        execute(deps.as_mut(), env.clone(), message_info(&bob, &[]), ExecuteMsg::Register {
+            identifier: "bob".to_string(),
+            claim_pending: false,
+            referrer: None,
+        })?;
+        execute(deps.as_mut(), env.clone(), message_info(&alice, &[Coin::new(1_000u128, "uxion")]), tip_msg("bob", 1_000, None))?;
+        execute(deps.as_mut(), env.clone(), message_info(&bob, &[Coin::new(500u128, "uxion")]), tip_msg("carol", 500, None))?;
+        execute(deps.as_mut(), env.clone(), message_info(&bob, &[Coin::new(3_000u128, "uxion")]), tip_msg("dave", 3_000, None))?;
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Stats { denom: "uxion".to_string() })?;
+        let stats: StatsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(stats.volume, Uint128::from(4_500u128));
+        assert_eq!(stats.tips, 3);
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TopSenders { denom: "uxion".to_string(), limit: None })?;
+        let top: LeaderboardResponse = cosmwasm_std::from_json(&res)?;
+        let senders: Vec<(String, u128)> = top.entries.iter().map(|e| (e.account.clone(), e.amount.u128())).collect();
+        assert_eq!(senders, vec![(bob.to_string(), 3_500), (alice.to_string(), 1_000)]);
+
+        // escrowed tips only count for the recipient once claimed
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TopRecipients { denom: "uxion".to_string(), limit: None })?;
+        let top: LeaderboardResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(top.entries.len(), 1);
+        assert_eq!(top.entries[0].account, "bob");
+
+        let dave = deps.api.addr_make("dave");
+        execute(deps.as_mut(), env.clone(), message_info(&dave, &[]), ExecuteMsg::Register { identifier: "dave".to_string(), claim_pending: false, referrer: None })?;
+        execute(deps.as_mut(), env.clone(), message_info(&dave, &[]), ExecuteMsg::Claim { identifier: "dave".to_string(), destination: None, denom: None, amount: None })?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TopRecipients { denom: "uxion".to_string(), limit: Some(1) })?;
+        let top: LeaderboardResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(top.entries, vec![LeaderboardEntry {
+            account: "dave".to_string(),
+            amount: Uint128::from(2_970u128),
+            count: 1,
+        }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pause_operations() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = deps.api.addr_make("admin");
+        let guardian = deps.api.addr_make("guardian");
+        let user = deps.api.addr_make("user");
+        let platform_wallet = deps.api.addr_make("platform_wallet").to_string();
+        instantiate(deps.as_mut(), env.clone(), message_info(&admin, &[]), InstantiateMsg {
+            admin: None,
+            guardian: Some(guardian.to_string()),
+            token_denom: "uxion".to_string(),
+            platform_wallet,
+            fee_shares: vec![],
+            tip_retention: RetentionPolicy::default(),
+            rate_limits: RateLimits::default(),
+            claim_link_ttl: None,
+            max_relayer_fee: None,
+            referral: ReferralPolicy::default(),
+            micro_tip_threshold: None,
+        })?;
+        let funded = message_info(&user, &[Coin::new(1_000u128, "uxion")]);
+        execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("erin@mail.com", 1_000, None))?;
+
+        // the guardian can stop inflows but not lock up escrowed funds
+        let err = execute(deps.as_mut(), env.clone(), message_info(&guardian, &[]), ExecuteMsg::Pause {
+            operations: vec![Operation::Refund],
+        }).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), message_info(&guardian, &[]), ExecuteMsg::Pause {
+            operations: vec![Operation::Register, Operation::Transfer],
+        })?;
+
+        let err = execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("erin@mail.com", 1_000, None)).unwrap_err();
+        assert_eq!(err, ContractError::Paused { operation: "transfer".to_string() });
+        let err = execute(deps.as_mut(), env.clone(), message_info(&user, &[]), ExecuteMsg::Register {
+            identifier: "user".to_string(),
+            claim_pending: false,
+            referrer: None,
+        }).unwrap_err();
+        assert_eq!(err, ContractError::Paused { operation: "register".to_string() });
+
+        // refunds stay open during the pause
+        execute(deps.as_mut(), env.clone(), message_info(&user, &[]), ExecuteMsg::Refund {
+            identifier: "erin@mail.com".to_string(),
+        })?;
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::PauseStatus {})?;
+        let status: PauseResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(status.paused, PauseState { register: true, transfer: true, claim: false, refund: false });
+
+        // only the admin can unpause
+        let unpause = ExecuteMsg::Unpause { operations: vec![Operation::Transfer] };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&guardian, &[]), unpause.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), unpause)?;
+        execute(deps.as_mut(), env.clone(), funded, tip_msg("erin@mail.com", 1_000, None))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocklist() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = deps.api.addr_make("admin");
+        let sender = deps.api.addr_make("sender");
+        let spammer = deps.api.addr_make("spammer");
+        let platform_wallet = deps.api.addr_make("platform_wallet").to_string();
+        instantiate(deps.as_mut(), env.clone(), message_info(&admin, &[]), InstantiateMsg {
+            admin: None,
+            guardian: None,
+            token_denom: "uxion".to_string(),
+            platform_wallet,
+            fee_shares: vec![],
+            tip_retention: RetentionPolicy::default(),
+            rate_limits: RateLimits::default(),
+            claim_link_ttl: None,
+            max_relayer_fee: None,
+            referral: ReferralPolicy::default(),
+            micro_tip_threshold: None,
+        })?;
+        execute(deps.as_mut(), env.clone(), message_info(&sender, &[Coin::new(1_000u128, "uxion")]), tip_msg("frank@mail.com", 1_000, None))?;
+
+        // only the admin manages the list, and every change is logged
+        let block = ExecuteMsg::Block {
+            target: BlockTarget::Identifier("frank@mail.com".to_string()),
+            reason: Some("takedown request".to_string()),
+        };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&sender, &[]), block.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), block)?;
+        assert_eq!(res.attributes[0].value, "block");
+        assert_eq!(res.events[0].ty, "social_tip.block");
+        assert_eq!(res.events[0].attributes[3].value, "takedown request");
+        execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), ExecuteMsg::Block {
+            target: BlockTarget::Address(spammer.to_string()),
+            reason: None,
+        })?;
+
+        // blocked senders and identifiers are rejected
+        let err = execute(deps.as_mut(), env.clone(), message_info(&spammer, &[Coin::new(1_000u128, "uxion")]), tip_msg("bob", 1_000, None)).unwrap_err();
+        assert_eq!(err, ContractError::Blocked { target: spammer.to_string() });
+        let err = execute(deps.as_mut(), env.clone(), message_info(&sender, &[]), ExecuteMsg::Register {
+            identifier: "frank@mail.com".to_string(),
+            claim_pending: false,
+            referrer: None,
+        }).unwrap_err();
+        assert_eq!(err, ContractError::Blocked { target: "frank@mail.com".to_string() });
+
+        // the escrow is frozen for the sender too, until the admin forces it back
+        let err = execute(deps.as_mut(), env.clone(), message_info(&sender, &[]), ExecuteMsg::Refund {
+            identifier: "frank@mail.com".to_string(),
+        }).unwrap_err();
+        assert_eq!(err, ContractError::Blocked { target: "frank@mail.com".to_string() });
+        let res = execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), ExecuteMsg::ForceRefund {
+            identifier: "frank@mail.com".to_string(),
+        })?;
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: vec![Coin::new(990u128, "uxion")],
+            })
+        );
+
+        // delisting lifts the block
+        execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), ExecuteMsg::Unblock {
+            target: BlockTarget::Identifier("frank@mail.com".to_string()),
+        })?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::IsBlocked {
+            target: BlockTarget::Identifier("frank@mail.com".to_string()),
+        })?;
+        let blocked: BlockedResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(blocked.entry, None);
+        execute(deps.as_mut(), env.clone(), message_info(&sender, &[]), ExecuteMsg::Register {
+            identifier: "frank@mail.com".to_string(),
+            claim_pending: false,
+            referrer: None,
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rate_limits() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let spammer = deps.api.addr_make("spammer");
+        let other = deps.api.addr_make("other");
+        let platform_wallet = deps.api.addr_make("platform_wallet").to_string();
+        instantiate(deps.as_mut(), env.clone(), message_info(&spammer, &[]), InstantiateMsg {
+            admin: None,
+            guardian: None,
+            token_denom: "uxion".to_string(),
+            platform_wallet,
+            fee_shares: vec![],
+            tip_retention: RetentionPolicy::default(),
+            rate_limits: RateLimits {
+                min_tips: vec![Coin::new(100u128, "uxion")],
+                window: 3_600,
+                max_escrows_per_sender: Some(2),
+                max_escrows_per_identifier: Some(3),
+            },
+            claim_link_ttl: None,
+            max_relayer_fee: None,
+            referral: ReferralPolicy::default(),
+            micro_tip_threshold: None,
+        })?;
+        let funded = message_info(&spammer, &[Coin::new(100u128, "uxion")]);
+
+        let err = execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("victim@mail.com", 99, None)).unwrap_err();
+        assert_eq!(err, ContractError::TipTooSmall { min: "100uxion".to_string() });
+
+        // two escrows per hour per sender
+        execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("victim@mail.com", 100, None))?;
+        execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("victim@mail.com", 100, None))?;
+        let err = execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("victim@mail.com", 100, None)).unwrap_err();
+        assert_eq!(err, ContractError::SenderRateLimited {});
+
+        // three escrows per hour per identifier, whoever sends them
+        let other_funded = message_info(&other, &[Coin::new(100u128, "uxion")]);
+        execute(deps.as_mut(), env.clone(), other_funded.clone(), tip_msg("victim@mail.com", 100, None))?;
+        let err = execute(deps.as_mut(), env.clone(), other_funded, tip_msg("victim@mail.com", 100, None)).unwrap_err();
+        assert_eq!(err, ContractError::InboundCapReached { identifier: "victim@mail.com".to_string() });
+        assert_eq!(err.to_string(), "Too many escrowed tips sent to victim@mail.com recently, try again later");
+
+        // the window slides with block time
+        env.block.time = env.block.time.plus_seconds(3_600);
+        execute(deps.as_mut(), env.clone(), funded, tip_msg("victim@mail.com", 100, None))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_claim_with_secret() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        setup_contract(&mut deps);
+        let sender = deps.api.addr_make("sender");
+        let grace = deps.api.addr_make("grace");
+        let mallory = deps.api.addr_make("mallory");
+        let cold_wallet = deps.api.addr_make("cold_wallet");
+        let funded = message_info(&sender, &[Coin::new(1_000u128, "uxion")]);
+
+        // two tips to the same email are held side by side
+        let res = execute(deps.as_mut(), env.clone(), funded.clone(), ExecuteMsg::Transfer {
+            identifier: "grace@mail.com".to_string(),
+            amount: Coin::new(1_000u128, "uxion"),
+            memo: None,
+            secret_hash: Some(secret_hash("link-secret")),
+            from_balance: false,
+        })?;
+        let escrow_id: u64 = res.events[0].attributes[1].value.parse().unwrap();
+        execute(deps.as_mut(), env.clone(), funded, tip_msg("grace@mail.com", 1_000, None))?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetEscrows {
+            identifier: "grace@mail.com".to_string(),
+            start_after: None,
+            limit: None,
+        })?;
+        let pending: EscrowsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(pending.escrows.len(), 2);
+
+        let claim = |secret: &str| ExecuteMsg::ClaimWithSecret {
+            escrow_id,
+            secret: secret.to_string(),
+            recipient: Some(cold_wallet.to_string()),
+            register: true,
+        };
+        let commit = |recipient: &cosmwasm_std::Addr| ExecuteMsg::CommitClaim {
+            escrow_id,
+            commitment: claim_reveal_hash("link-secret", recipient),
+        };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&grace, &[]), claim("guess")).unwrap_err();
+        assert_eq!(err, ContractError::Std(StdError::generic_err("Invalid claim secret")));
+
+        // the secret alone is not enough, it has to be committed to first, in
+        // an earlier block
+        let err = execute(deps.as_mut(), env.clone(), message_info(&grace, &[]), claim("link-secret")).unwrap_err();
+        assert_eq!(err, ContractError::Std(StdError::generic_err("No claim commitment for this secret and recipient")));
+        let res = execute(deps.as_mut(), env.clone(), message_info(&grace, &[]), commit(&cold_wallet))?;
+        assert_eq!(res.events[0].ty, "social_tip.claim_commit");
+        let err = execute(deps.as_mut(), env.clone(), message_info(&grace, &[]), claim("link-secret")).unwrap_err();
+        assert_eq!(err, ContractError::Std(StdError::generic_err("Claim commitment must be from an earlier block")));
+        env.block.height += 1;
+
+        // copying the revealed secret from the mempool does not let anyone
+        // else redirect the claim, their commitment comes too late
+        let front_run = ExecuteMsg::ClaimWithSecret {
+            escrow_id,
+            secret: "link-secret".to_string(),
+            recipient: None,
+            register: false,
+        };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&mallory, &[]), front_run.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Std(StdError::generic_err("No claim commitment for this secret and recipient")));
+        execute(deps.as_mut(), env.clone(), message_info(&mallory, &[]), commit(&mallory))?;
+        let err = execute(deps.as_mut(), env.clone(), message_info(&mallory, &[]), front_run).unwrap_err();
+        assert_eq!(err, ContractError::Std(StdError::generic_err("Claim commitment must be from an earlier block")));
+
+        // the secret pays the committed recipient and registers the email to it
+        let res = execute(deps.as_mut(), env.clone(), message_info(&grace, &[]), claim("link-secret"))?;
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: cold_wallet.to_string(),
+                amount: vec![Coin::new(990u128, "uxion")],
+            })
         );
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetAccount { identifier: "grace@mail.com".to_string() })?;
+        let account: AccountResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(account.address, Some(cold_wallet.clone()));
+
+        // the link is single use, the other tip is still waiting
+        let err = execute(deps.as_mut(), env.clone(), message_info(&grace, &[]), claim("link-secret")).unwrap_err();
+        assert_eq!(err, ContractError::Std(StdError::generic_err("No escrowed tokens found")));
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetEscrow { identifier: "grace@mail.com".to_string() })?;
+        let escrow: EscrowResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(escrow.escrow.unwrap().secret_hash, None);
+
+        // expired links no longer work
+        let sender_funded = message_info(&sender, &[Coin::new(1_000u128, "uxion")]);
+        let res = execute(deps.as_mut(), env.clone(), sender_funded, ExecuteMsg::Transfer {
+            identifier: "heidi@mail.com".to_string(),
+            amount: Coin::new(1_000u128, "uxion"),
+            memo: None,
+            secret_hash: Some(secret_hash("other-secret")),
+            from_balance: false,
+        })?;
+        let escrow_id: u64 = res.events[0].attributes[1].value.parse().unwrap();
+        env.block.time = env.block.time.plus_seconds(30 * 24 * 60 * 60);
+        let err = execute(deps.as_mut(), env.clone(), message_info(&grace, &[]), ExecuteMsg::CommitClaim {
+            escrow_id,
+            commitment: claim_reveal_hash("other-secret", &grace),
+        }).unwrap_err();
+        assert_eq!(err, ContractError::Std(StdError::generic_err("Claim link has expired")));
+        let err = execute(deps.as_mut(), env.clone(), message_info(&grace, &[]), ExecuteMsg::ClaimWithSecret {
+            escrow_id,
+            secret: "other-secret".to_string(),
+            recipient: None,
+            register: false,
+        }).unwrap_err();
+        assert_eq!(err, ContractError::Std(StdError::generic_err("Claim link has expired")));
 
         Ok(())
+    }
+
+    #[test]
+    fn test_register_leaves_secret_escrows() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup_contract(&mut deps);
+        let sender = deps.api.addr_make("sender");
+        let mallory = deps.api.addr_make("mallory");
+        let funded = message_info(&sender, &[Coin::new(1_000u128, "uxion")]);
+        execute(deps.as_mut(), env.clone(), funded.clone(), ExecuteMsg::Transfer {
+            identifier: "ivy@mail.com".to_string(),
+            amount: Coin::new(1_000u128, "uxion"),
+            memo: None,
+            secret_hash: Some(secret_hash("link-secret")),
+            from_balance: false,
+        })?;
+        let funded = message_info(&sender, &[Coin::new(500u128, "uxion")]);
+        execute(deps.as_mut(), env.clone(), funded, tip_msg("ivy@mail.com", 500, None))?;
+
+        // whoever registers the email first only gets the tip without a link
+        let res = execute(deps.as_mut(), env.clone(), message_info(&mallory, &[]), ExecuteMsg::Register {
+            identifier: "ivy@mail.com".to_string(),
+            claim_pending: true,
+            referrer: None,
+        })?;
+        assert_eq!(
+            res.messages,
+            vec![cosmwasm_std::SubMsg::new(BankMsg::Send {
+                to_address: mallory.to_string(),
+                amount: vec![Coin::new(495u128, "uxion")],
+            })]
+        );
+        let err = execute(deps.as_mut(), env.clone(), message_info(&mallory, &[]), ExecuteMsg::Claim {
+            identifier: "ivy@mail.com".to_string(),
+            destination: None,
+            denom: None,
+            amount: None,
+        }).unwrap_err();
+        assert_eq!(err, ContractError::Std(StdError::generic_err("No escrowed tokens found")));
 
+        // the escrow behind the link stays refundable
+        let res = execute(deps.as_mut(), env.clone(), message_info(&sender, &[]), ExecuteMsg::Refund {
+            identifier: "ivy@mail.com".to_string(),
+        })?;
+        assert_eq!(
+            res.messages,
+            vec![cosmwasm_std::SubMsg::new(BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: vec![Coin::new(990u128, "uxion")],
+            })]
+        );
+
+        Ok(())
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_partial_claim_to_destination() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup_contract(&mut deps);
+        let sender = deps.api.addr_make("sender");
+        let ivan = deps.api.addr_make("ivan");
+        let cold_wallet = deps.api.addr_make("cold_wallet");
+        let funded = message_info(&sender, &[Coin::new(1_000u128, "uxion")]);
+        execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("ivan@mail.com", 1_000, None))?;
+        execute(deps.as_mut(), env.clone(), funded, tip_msg("ivan@mail.com", 1_000, None))?;
+        execute(deps.as_mut(), env.clone(), message_info(&ivan, &[]), ExecuteMsg::Register {
+            identifier: "ivan@mail.com".to_string(),
+            claim_pending: false,
+            referrer: None,
+        })?;
+
+        let partial = |amount: u128| ExecuteMsg::Claim {
+            identifier: "ivan@mail.com".to_string(),
+            destination: Some(cold_wallet.to_string()),
+            denom: Some("uxion".to_string()),
+            amount: Some(Uint128::from(amount)),
+        };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&ivan, &[]), partial(2_000)).unwrap_err();
+        assert_eq!(err, ContractError::Std(StdError::generic_err("Not enough escrowed tokens")));
+        let err = execute(deps.as_mut(), env.clone(), message_info(&ivan, &[]), partial(0)).unwrap_err();
+        assert_eq!(err, ContractError::ZeroClaimAmount {});
+
+        // 1_200 drains the first escrow and takes 210 from the second
+        let res = execute(deps.as_mut(), env.clone(), message_info(&ivan, &[]), partial(1_200))?;
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: cold_wallet.to_string(),
+                amount: vec![Coin::new(1_200u128, "uxion")],
+            })
+        );
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetEscrows {
+            identifier: "ivan@mail.com".to_string(),
+            start_after: None,
+            limit: None,
+        })?;
+        let pending: EscrowsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(pending.escrows.len(), 1);
+        assert_eq!(pending.escrows[0].amount, Coin::new(780u128, "uxion"));
+
+        // a plain claim takes the rest to the caller
+        let res = execute(deps.as_mut(), env.clone(), message_info(&ivan, &[]), ExecuteMsg::Claim {
+            identifier: "ivan@mail.com".to_string(),
+            destination: None,
+            denom: None,
+            amount: None,
+        })?;
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ivan.to_string(),
+                amount: vec![Coin::new(780u128, "uxion")],
+            })
+        );
+
+        // two tips, however many claims they took
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TopRecipients { denom: "uxion".to_string(), limit: None })?;
+        let top: LeaderboardResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(top.entries, vec![LeaderboardEntry {
+            account: "ivan@mail.com".to_string(),
+            amount: Uint128::from(1_980u128),
+            count: 2,
+        }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_claims_pending() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup_contract(&mut deps);
+        let sender = deps.api.addr_make("sender");
+        let judy = deps.api.addr_make("judy");
+        let funded = message_info(&sender, &[Coin::new(1_000u128, "uxion")]);
+        execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("judy@mail.com", 1_000, None))?;
+        execute(deps.as_mut(), env.clone(), funded, tip_msg("judy@mail.com", 1_000, None))?;
+
+        let res = execute(deps.as_mut(), env.clone(), message_info(&judy, &[]), ExecuteMsg::Register {
+            identifier: "judy@mail.com".to_string(),
+            claim_pending: true,
+            referrer: None,
+        })?;
+
+        // one bank message for everything, one claim event per delivered tip
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: judy.to_string(),
+                amount: vec![Coin::new(1_980u128, "uxion")],
+            })
+        );
+        let delivered: Vec<&str> = res.events.iter().map(|e| e.ty.as_str()).collect();
+        assert_eq!(delivered, vec!["social_tip.register", "social_tip.claim", "social_tip.claim"]);
+        assert!(res.attributes.iter().any(|a| a.key == "delivered" && a.value == "1980uxion"));
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetEscrow { identifier: "judy@mail.com".to_string() })?;
+        let escrow: EscrowResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(escrow.escrow, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relay_claim() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let platform_wallet = deps.api.addr_make("platform_wallet").to_string();
+        let creator = deps.api.addr_make("creator");
+        instantiate(deps.as_mut(), env.clone(), message_info(&creator, &[]), InstantiateMsg {
+            admin: None,
+            guardian: None,
+            token_denom: "uxion".to_string(),
+            platform_wallet,
+            fee_shares: vec![],
+            tip_retention: RetentionPolicy::default(),
+            rate_limits: RateLimits::default(),
+            claim_link_ttl: None,
+            max_relayer_fee: Some(Uint128::new(50)),
+            referral: ReferralPolicy::default(),
+            micro_tip_threshold: None,
+        })?;
+        let sender = deps.api.addr_make("sender");
+        let relayer = message_info(&deps.api.addr_make("relayer"), &[]);
+        let funded = message_info(&sender, &[Coin::new(1_000u128, "uxion")]);
+
+        // the recipient's account is the one behind their ed25519 key
+        let key = ed25519_zebra::SigningKey::from([7u8; 32]);
+        let pubkey = ed25519_zebra::VerificationKey::from(&key).as_ref().to_vec();
+        let kim = pubkey_to_addr(&deps.api, &SignatureScheme::Ed25519, &pubkey)?;
+        execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("kim@mail.com", 1_000, None))?;
+        execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("kim@mail.com", 1_000, None))?;
+        execute(deps.as_mut(), env.clone(), message_info(&kim, &[]), ExecuteMsg::Register {
+            identifier: "kim@mail.com".to_string(),
+            claim_pending: false,
+            referrer: None,
+        })?;
+
+        let payload = |nonce: u64, chain_id: &str, relayer_fee: u128| ClaimPayload {
+            chain_id: chain_id.to_string(),
+            contract: env.contract.address.to_string(),
+            identifier: "kim@mail.com".to_string(),
+            nonce,
+            destination: None,
+            relayer: None,
+            relayer_fee: Uint128::new(relayer_fee),
+        };
+        let relay = |payload: ClaimPayload| {
+            let signature: [u8; 64] = key.sign(&sign_bytes(&payload).unwrap()).into();
+            ExecuteMsg::RelayClaim {
+                payload,
+                scheme: SignatureScheme::Ed25519,
+                pubkey: pubkey.clone().into(),
+                signature: signature.to_vec().into(),
+            }
+        };
+        let chain_id = env.block.chain_id.clone();
+
+        // signed for another chain, over the fee cap, or tampered with
+        let err = execute(deps.as_mut(), env.clone(), relayer.clone(), relay(payload(0, "other-chain", 10))).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Claim payload is for another chain or contract").into());
+        let err = execute(deps.as_mut(), env.clone(), relayer.clone(), relay(payload(0, &chain_id, 51))).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Relayer fee exceeds the configured cap").into());
+        let mut tampered = relay(payload(0, &chain_id, 10));
+        if let ExecuteMsg::RelayClaim { payload, .. } = &mut tampered {
+            payload.relayer_fee = Uint128::new(50);
+        }
+        let err = execute(deps.as_mut(), env.clone(), relayer.clone(), tampered).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Invalid claim signature").into());
+
+        // the relayer is paid from the claim, the rest goes to the recipient
+        let res = execute(deps.as_mut(), env.clone(), relayer.clone(), relay(payload(0, &chain_id, 10)))?;
+        assert_eq!(
+            res.messages.iter().map(|m| m.msg.clone()).collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::Bank(BankMsg::Send { to_address: kim.to_string(), amount: vec![Coin::new(1_970u128, "uxion")] }),
+                CosmosMsg::Bank(BankMsg::Send { to_address: relayer.sender.to_string(), amount: vec![Coin::new(10u128, "uxion")] }),
+            ]
+        );
+        assert!(res.events.iter().any(|e| e.ty == "social_tip.relay"));
+        // the relayer's cut is not counted as received by kim
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TopRecipients { denom: "uxion".to_string(), limit: None })?;
+        let top: LeaderboardResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(top.entries, vec![LeaderboardEntry {
+            account: "kim@mail.com".to_string(),
+            amount: Uint128::from(1_970u128),
+            count: 2,
+        }]);
+
+        // the same signature cannot be replayed
+        let err = execute(deps.as_mut(), env.clone(), relayer.clone(), relay(payload(0, &chain_id, 10))).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Invalid nonce, expected 1").into());
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::RelayNonce { address: kim.to_string() })?;
+        let nonce: RelayNonceResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(nonce.nonce, 1);
+
+        // a key that does not own the identifier is rejected
+        let other = ed25519_zebra::SigningKey::from([8u8; 32]);
+        let msg = payload(1, &chain_id, 0);
+        let signature: [u8; 64] = other.sign(&sign_bytes(&msg)?).into();
+        let err = execute(deps.as_mut(), env.clone(), relayer.clone(), ExecuteMsg::RelayClaim {
+            payload: msg,
+            scheme: SignatureScheme::Ed25519,
+            pubkey: ed25519_zebra::VerificationKey::from(&other).as_ref().to_vec().into(),
+            signature: signature.to_vec().into(),
+        }).unwrap_err();
+        assert_eq!(err, StdError::generic_err("identifier is not registered to signer").into());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relay_claim_secp256k1() -> Result<(), ContractError> {
+        use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup_contract(&mut deps);
+        let sender = deps.api.addr_make("sender");
+        let relayer = message_info(&deps.api.addr_make("relayer"), &[]);
+
+        let key = SigningKey::from_bytes(&[9u8; 32].into()).unwrap();
+        let pubkey = key.verifying_key().to_encoded_point(true).as_bytes().to_vec();
+        let lee = pubkey_to_addr(&deps.api, &SignatureScheme::Secp256k1, &pubkey)?;
+        execute(deps.as_mut(), env.clone(), message_info(&sender, &[Coin::new(1_000u128, "uxion")]), tip_msg("lee@mail.com", 1_000, None))?;
+        execute(deps.as_mut(), env.clone(), message_info(&lee, &[]), ExecuteMsg::Register {
+            identifier: "lee@mail.com".to_string(),
+            claim_pending: false,
+            referrer: None,
+        })?;
+
+        // no fee cap configured, so the relayer works for free
+        let payload = ClaimPayload {
+            chain_id: env.block.chain_id.clone(),
+            contract: env.contract.address.to_string(),
+            identifier: "lee@mail.com".to_string(),
+            nonce: 0,
+            destination: None,
+            relayer: Some(relayer.sender.to_string()),
+            relayer_fee: Uint128::zero(),
+        };
+        let signature: Signature = key.sign(&sign_bytes(&payload)?);
+        let msg = ExecuteMsg::RelayClaim {
+            payload,
+            scheme: SignatureScheme::Secp256k1,
+            pubkey: pubkey.into(),
+            signature: signature.to_bytes().to_vec().into(),
+        };
+
+        // only the named relayer may submit it
+        let err = execute(deps.as_mut(), env.clone(), message_info(&sender, &[]), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = execute(deps.as_mut(), env.clone(), relayer, msg)?;
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send { to_address: lee.to_string(), amount: vec![Coin::new(990u128, "uxion")] })
+        );
+        assert_eq!(res.messages.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fee_split_and_withdrawal() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = message_info(&deps.api.addr_make("creator"), &[]);
+        let treasury = deps.api.addr_make("treasury").to_string();
+        let referral_pool = deps.api.addr_make("referral_pool").to_string();
+        let community = deps.api.addr_make("community").to_string();
+        instantiate(deps.as_mut(), env.clone(), admin.clone(), InstantiateMsg {
+            admin: None,
+            guardian: None,
+            token_denom: "uxion".to_string(),
+            platform_wallet: treasury.clone(),
+            fee_shares: vec![
+                FeeShareMsg { address: treasury.clone(), bps: 7_000 },
+                FeeShareMsg { address: referral_pool.clone(), bps: 2_000 },
+                FeeShareMsg { address: community.clone(), bps: 1_000 },
+            ],
+            tip_retention: RetentionPolicy::default(),
+            rate_limits: RateLimits::default(),
+            claim_link_ttl: None,
+            max_relayer_fee: None,
+            referral: ReferralPolicy::default(),
+            micro_tip_threshold: None,
+        })?;
+        let sender = deps.api.addr_make("sender");
+        let funded = message_info(&sender, &[Coin::new(5_000u128, "uxion")]);
+
+        // nothing to withdraw yet
+        let err = execute(deps.as_mut(), env.clone(), funded.clone(), ExecuteMsg::WithdrawFees {}).unwrap_err();
+        assert_eq!(err, StdError::generic_err("No fees to withdraw").into());
+
+        // two tips accrue 50 + 50 in fees without any bank message for them
+        for _ in 0..2 {
+            let res = execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("mia@mail.com", 5_000, None))?;
+            assert!(res.messages.is_empty());
+        }
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::FeePool {})?;
+        let pool: FeePoolResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(pool.accrued, vec![Coin::new(100u128, "uxion")]);
+
+        // anyone may trigger the payout, which follows the configured split
+        let res = execute(deps.as_mut(), env.clone(), funded.clone(), ExecuteMsg::WithdrawFees {})?;
+        let mut paid: Vec<(String, Vec<Coin>)> = res.messages.iter().map(|m| match &m.msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => (to_address.clone(), amount.clone()),
+            _ => panic!("unexpected message"),
+        }).collect();
+        paid.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut expected = vec![
+            (treasury.clone(), vec![Coin::new(70u128, "uxion")]),
+            (referral_pool.clone(), vec![Coin::new(20u128, "uxion")]),
+            (community.clone(), vec![Coin::new(10u128, "uxion")]),
+        ];
+        expected.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(paid, expected);
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::FeePool {})?;
+        let pool: FeePoolResponse = cosmwasm_std::from_json(&res)?;
+        assert!(pool.accrued.is_empty());
+
+        // only the admin may change the split, and it must add up to 100%
+        let shares = vec![FeeShareMsg { address: community.clone(), bps: 10_000 }];
+        let err = execute(deps.as_mut(), env.clone(), funded, ExecuteMsg::SetFeeShares { shares: shares.clone() }).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), env.clone(), admin.clone(), ExecuteMsg::SetFeeShares {
+            shares: vec![FeeShareMsg { address: community.clone(), bps: 9_000 }],
+        }).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Fee shares must add up to 10000 bps").into());
+        execute(deps.as_mut(), env.clone(), admin, ExecuteMsg::SetFeeShares { shares: shares.clone() })?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::FeePool {})?;
+        let pool: FeePoolResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(pool.shares, vec![FeeShare { address: deps.api.addr_make("community"), bps: 10_000 }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_referral_rewards() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let platform_wallet = deps.api.addr_make("platform_wallet").to_string();
+        let creator = deps.api.addr_make("creator");
+        instantiate(deps.as_mut(), env.clone(), message_info(&creator, &[]), InstantiateMsg {
+            admin: None,
+            guardian: None,
+            token_denom: "uxion".to_string(),
+            platform_wallet,
+            fee_shares: vec![],
+            tip_retention: RetentionPolicy::default(),
+            rate_limits: RateLimits::default(),
+            claim_link_ttl: None,
+            max_relayer_fee: None,
+            // a quarter of the fee for 30 days
+            referral: ReferralPolicy { bps: 2_500, duration: 30 * 86_400 },
+            micro_tip_threshold: None,
+        })?;
+        let sender = deps.api.addr_make("sender");
+        let olga = deps.api.addr_make("olga");
+        let pete = deps.api.addr_make("pete");
+        let funded = message_info(&sender, &[Coin::new(10_000u128, "uxion")]);
+        execute(deps.as_mut(), env.clone(), message_info(&olga, &[]), ExecuteMsg::Register {
+            identifier: "olga".to_string(),
+            claim_pending: false,
+            referrer: None,
+        })?;
+
+        // unknown referrers and self-referrals are rejected
+        let err = execute(deps.as_mut(), env.clone(), message_info(&pete, &[]), ExecuteMsg::Register {
+            identifier: "pete".to_string(),
+            claim_pending: false,
+            referrer: Some("nobody".to_string()),
+        }).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Referrer is not registered").into());
+        let err = execute(deps.as_mut(), env.clone(), message_info(&olga, &[]), ExecuteMsg::Register {
+            identifier: "olga2".to_string(),
+            claim_pending: false,
+            referrer: Some("olga".to_string()),
+        }).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Cannot refer yourself").into());
+
+        execute(deps.as_mut(), env.clone(), message_info(&pete, &[]), ExecuteMsg::Register {
+            identifier: "pete".to_string(),
+            claim_pending: false,
+            referrer: Some("olga".to_string()),
+        })?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetReferral { identifier: "pete".to_string() })?;
+        let referral: ReferralResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(referral.referral, Some(Referral { referrer: "olga".to_string(), since: env.block.time }));
+        assert_eq!(referral.rewards_until, Some(env.block.time.plus_days(30)));
+
+        // the tipper pays the usual 1% and pete the usual net, olga gets 25 of the 100 fee
+        let res = execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("pete", 10_000, None))?;
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send { to_address: pete.to_string(), amount: vec![Coin::new(9_900u128, "uxion")] })
+        );
+        let reward = res.events.iter().find(|e| e.ty == "social_tip.referral_reward").unwrap();
+        assert!(reward.attributes.iter().any(|a| a.key == "amount" && a.value == "25"));
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::FeePool {})?;
+        let pool: FeePoolResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(pool.accrued, vec![Coin::new(75u128, "uxion")]);
+
+        // after the reward period the whole fee goes back to the pool
+        env.block.time = env.block.time.plus_days(30);
+        let res = execute(deps.as_mut(), env.clone(), funded, tip_msg("pete", 10_000, None))?;
+        assert!(!res.events.iter().any(|e| e.ty == "social_tip.referral_reward"));
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::FeePool {})?;
+        let pool: FeePoolResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(pool.accrued, vec![Coin::new(175u128, "uxion")]);
+
+        // only olga may claim her rewards, once
+        let err = execute(deps.as_mut(), env.clone(), message_info(&pete, &[]), ExecuteMsg::ClaimReferralRewards {
+            identifier: "olga".to_string(),
+        }).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let claim = ExecuteMsg::ClaimReferralRewards { identifier: "olga".to_string() };
+        let res = execute(deps.as_mut(), env.clone(), message_info(&olga, &[]), claim.clone())?;
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send { to_address: olga.to_string(), amount: vec![Coin::new(25u128, "uxion")] })
+        );
+        let err = execute(deps.as_mut(), env.clone(), message_info(&olga, &[]), claim).unwrap_err();
+        assert_eq!(err, StdError::generic_err("No referral rewards to claim").into());
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::ReferralEarnings { identifier: "olga".to_string() })?;
+        let earnings: ReferralEarningsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(earnings.earnings, vec![ReferralEarning {
+            denom: "uxion".to_string(),
+            earned: Uint128::new(25),
+            unpaid: Uint128::zero(),
+        }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_validation() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let creator = message_info(&deps.api.addr_make("creator"), &[]);
+        let platform_wallet = deps.api.addr_make("platform_wallet");
+        let msg = InstantiateMsg {
+            admin: None,
+            guardian: None,
+            token_denom: "uxion".to_string(),
+            platform_wallet: platform_wallet.to_string(),
+            fee_shares: vec![],
+            tip_retention: RetentionPolicy::default(),
+            rate_limits: RateLimits::default(),
+            claim_link_ttl: None,
+            max_relayer_fee: None,
+            referral: ReferralPolicy::default(),
+            micro_tip_threshold: None,
+        };
+
+        // a mistyped fee wallet or denom fails at deploy time, not on every transfer
+        let typo = InstantiateMsg { platform_wallet: "platfrom_wallet".to_string(), ..msg.clone() };
+        assert!(instantiate(deps.as_mut(), env.clone(), creator.clone(), typo).is_err());
+        let empty = InstantiateMsg { token_denom: "".to_string(), ..msg.clone() };
+        let err = instantiate(deps.as_mut(), env.clone(), creator.clone(), empty).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Denom must not be empty"));
+        let bad = InstantiateMsg { token_denom: "1xion".to_string(), ..msg.clone() };
+        let err = instantiate(deps.as_mut(), env.clone(), creator.clone(), bad).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Invalid denom `1xion`"));
+        let ibc = InstantiateMsg { token_denom: "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2".to_string(), ..msg.clone() };
+        instantiate(deps.as_mut(), env.clone(), creator.clone(), ibc)?;
+        instantiate(deps.as_mut(), env.clone(), creator.clone(), msg)?;
+
+        // updates go through the same checks and leave the config untouched on failure
+        let err = execute(deps.as_mut(), env.clone(), creator.clone(), ExecuteMsg::UpdateConfig(ConfigUpdate {
+            platform_wallet: Some("platfrom_wallet".to_string()),
+            ..ConfigUpdate::default()
+        })).unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+        let err = execute(deps.as_mut(), env.clone(), creator.clone(), ExecuteMsg::UpdateConfig(ConfigUpdate {
+            claim_link_ttl: Some(0),
+            ..ConfigUpdate::default()
+        })).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Claim link TTL must be positive").into());
+        let err = execute(deps.as_mut(), env.clone(), creator.clone(), ExecuteMsg::UpdateConfig(ConfigUpdate {
+            rate_limits: Some(RateLimits { max_escrows_per_identifier: Some(3), ..RateLimits::default() }),
+            ..ConfigUpdate::default()
+        })).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Escrow caps need a positive window").into());
+        let err = execute(deps.as_mut(), env.clone(), message_info(&platform_wallet, &[]), ExecuteMsg::UpdateConfig(ConfigUpdate {
+            claim_link_ttl: Some(60),
+            ..ConfigUpdate::default()
+        })).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let treasury = deps.api.addr_make("treasury");
+        execute(deps.as_mut(), env.clone(), creator.clone(), ExecuteMsg::UpdateConfig(ConfigUpdate {
+            platform_wallet: Some(treasury.to_string()),
+            fee_shares: Some(vec![FeeShareMsg { address: treasury.to_string(), bps: 10_000 }]),
+            claim_link_ttl: Some(60),
+            ..ConfigUpdate::default()
+        }))?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {})?;
+        let config: Config = cosmwasm_std::from_json(&res)?;
+        assert_eq!(config.platform_wallet, treasury);
+        assert_eq!(config.fee_shares, vec![FeeShare { address: treasury.clone(), bps: 10_000 }]);
+        assert_eq!(config.claim_link_ttl, 60);
+
+        // a new platform wallet takes the fees along while it had all of them
+        let vault = deps.api.addr_make("vault");
+        execute(deps.as_mut(), env.clone(), creator.clone(), ExecuteMsg::UpdateConfig(ConfigUpdate {
+            platform_wallet: Some(vault.to_string()),
+            ..ConfigUpdate::default()
+        }))?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {})?;
+        let config: Config = cosmwasm_std::from_json(&res)?;
+        assert_eq!(config.fee_shares, vec![FeeShare { address: vault.clone(), bps: 10_000 }]);
+        // but leaves a custom split alone
+        let split = vec![
+            FeeShare { address: vault.clone(), bps: 6_000 },
+            FeeShare { address: treasury.clone(), bps: 4_000 },
+        ];
+        execute(deps.as_mut(), env.clone(), creator.clone(), ExecuteMsg::UpdateConfig(ConfigUpdate {
+            fee_shares: Some(split.iter().map(|s| FeeShareMsg { address: s.address.to_string(), bps: s.bps }).collect()),
+            ..ConfigUpdate::default()
+        }))?;
+        execute(deps.as_mut(), env.clone(), creator.clone(), ExecuteMsg::UpdateConfig(ConfigUpdate {
+            platform_wallet: Some(treasury.to_string()),
+            ..ConfigUpdate::default()
+        }))?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {})?;
+        let config: Config = cosmwasm_std::from_json(&res)?;
+        assert_eq!(config.platform_wallet, treasury);
+        assert_eq!(config.fee_shares, split);
+
+        // migrate re-checks what is stored
+        let res = migrate(deps.as_mut(), env.clone(), MigrateMsg {})?;
+        assert_eq!(res.events[0].ty, "social_tip.migrate");
+        let mut broken = config;
+        broken.platform_wallet = cosmwasm_std::Addr::unchecked("platfrom_wallet");
+        crate::state::CONFIG.save(deps.as_mut().storage, &broken)?;
+        assert!(migrate(deps.as_mut(), env, MigrateMsg {}).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_from_baseline() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let platform_wallet = deps.api.addr_make("platform_wallet");
+        // what the first release left in storage: a two-field config and no
+        // contract version
+        let legacy = format!(r#"{{"token_denom":"uxion","platform_wallet":"{}"}}"#, platform_wallet);
+        cosmwasm_std::Storage::set(deps.as_mut().storage, b"config", legacy.as_bytes());
+
+        let res = migrate(deps.as_mut(), env.clone(), MigrateMsg {})?;
+        let from = res.events[0].attributes.iter().find(|a| a.key == "from_version").unwrap();
+        assert_eq!(from.value, "unversioned");
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {})?;
+        let config: Config = cosmwasm_std::from_json(&res)?;
+        assert_eq!(config.admin, platform_wallet);
+        assert_eq!(config.token_denom, "uxion");
+        assert_eq!(config.fee_shares, vec![FeeShare { address: platform_wallet.clone(), bps: 10_000 }]);
+        assert_eq!(config.claim_link_ttl, 30 * 24 * 60 * 60);
+        assert_eq!(cw2::get_contract_version(deps.as_ref().storage)?.contract, "crates.io:social-tip-contract");
+
+        // the upgraded contract takes tips and can be migrated again
+        let sender = deps.api.addr_make("sender");
+        execute(deps.as_mut(), env.clone(), message_info(&sender, &[Coin::new(1_000u128, "uxion")]), tip_msg("dave@mail.com", 1_000, None))?;
+        migrate(deps.as_mut(), env.clone(), MigrateMsg {})?;
+
+        // a wallet the first release stored unchecked stops the migration
+        let mut deps = mock_dependencies();
+        cosmwasm_std::Storage::set(deps.as_mut().storage, b"config", br#"{"token_denom":"uxion","platform_wallet":"platfrom_wallet"}"#);
+        assert!(migrate(deps.as_mut(), env, MigrateMsg {}).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_baseline_escrows() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let platform_wallet = deps.api.addr_make("platform_wallet");
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let carol = deps.api.addr_make("carol");
+        // the first release's storage, byte for byte: its config and one
+        // escrow per identifier in the "escrows" map
+        let storage = deps.as_mut().storage;
+        let legacy = format!(r#"{{"token_denom":"uxion","platform_wallet":"{}"}}"#, platform_wallet);
+        cosmwasm_std::Storage::set(storage, b"config", legacy.as_bytes());
+        for (identifier, sender, amount) in [("carol@mail.com", &alice, 990), ("dave@mail.com", &bob, 495), ("zero@mail.com", &bob, 0)] {
+            let key = [b"\x00\x07escrows".as_slice(), identifier.as_bytes()].concat();
+            let escrow = format!(r#"{{"sender":"{}","amount":{{"denom":"uxion","amount":"{}"}}}}"#, sender, amount);
+            cosmwasm_std::Storage::set(storage, &key, escrow.as_bytes());
+        }
+
+        let res = migrate(deps.as_mut(), env.clone(), MigrateMsg {})?;
+        assert!(res.attributes.contains(&cosmwasm_std::attr("legacy_escrows", "2")));
+        assert!(crate::state::LEGACY_ESCROWS.is_empty(deps.as_ref().storage));
+
+        // each escrow now has its own id and an escrowed tip record
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetEscrows {
+            identifier: "carol@mail.com".to_string(),
+            start_after: None,
+            limit: None,
+        })?;
+        let held: EscrowsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(held.escrows.len(), 1);
+        assert_eq!(held.escrows[0].sender, alice);
+        assert_eq!(held.escrows[0].amount, Coin::new(990u128, "uxion"));
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetTip { id: held.escrows[0].tip_id })?;
+        let tip = cosmwasm_std::from_json::<crate::msg::TipResponse>(&res)?.tip.unwrap();
+        assert_eq!(tip.status, TipStatus::Escrowed);
+        assert_eq!((tip.gross, tip.fee, tip.net), (Uint128::new(990), Uint128::zero(), Uint128::new(990)));
+
+        // and can be claimed or refunded again
+        execute(deps.as_mut(), env.clone(), message_info(&carol, &[]), ExecuteMsg::Register {
+            identifier: "carol@mail.com".to_string(),
+            claim_pending: true,
+            referrer: None,
+        })?;
+        let res = execute(deps.as_mut(), env.clone(), message_info(&bob, &[]), ExecuteMsg::Refund {
+            identifier: "dave@mail.com".to_string(),
+        })?;
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: bob.to_string(),
+                amount: vec![Coin::new(495u128, "uxion")],
+            })
+        );
+        let res = query(deps.as_ref(), env, QueryMsg::TipsReceived {
+            identifier: "carol@mail.com".to_string(),
+            start_after: None,
+            limit: None,
+        })?;
+        let received: TipsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(received.tips[0].status, TipStatus::Claimed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profiles() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup_contract(&mut deps);
+        let quinn = deps.api.addr_make("quinn");
+        let other = deps.api.addr_make("other");
+        execute(deps.as_mut(), env.clone(), message_info(&quinn, &[]), ExecuteMsg::Register {
+            identifier: "quinn".to_string(),
+            claim_pending: false,
+            referrer: None,
+        })?;
+
+        let profile = Profile {
+            display_name: Some("Quinn".to_string()),
+            avatar_uri: Some("ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_string()),
+            bio: Some("Streams chess.\nTips welcome.".to_string()),
+            links: vec!["https://example.com/quinn".to_string()],
+            payout_denom: Some("uxion".to_string()),
+        };
+        let set = |profile: Profile| ExecuteMsg::SetProfile { identifier: "quinn".to_string(), profile };
+
+        // only the owner edits, and content is checked
+        let err = execute(deps.as_mut(), env.clone(), message_info(&other, &[]), set(profile.clone())).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let cases = [
+            (Profile { display_name: Some("q".repeat(65)), ..profile.clone() }, "Display name exceeds 64 bytes"),
+            (Profile { display_name: Some("  ".to_string()), ..profile.clone() }, "Display name must not be blank"),
+            (Profile { bio: Some("hi\u{7}".to_string()), ..profile.clone() }, "Bio contains control characters"),
+            (Profile { avatar_uri: Some("javascript:alert(1)".to_string()), ..profile.clone() }, "URI must start with https:// or ipfs://"),
+            (Profile { links: vec!["http://example.com".to_string()], ..profile.clone() }, "URI must start with https://"),
+            (Profile { links: vec!["https://a.io".to_string(); 6], ..profile.clone() }, "At most 5 profile links"),
+            (Profile { payout_denom: Some("".to_string()), ..profile.clone() }, "Denom must not be empty"),
+        ];
+        for (bad, msg) in cases {
+            let err = execute(deps.as_mut(), env.clone(), message_info(&quinn, &[]), set(bad)).unwrap_err();
+            assert_eq!(err, StdError::generic_err(msg).into());
+        }
+
+        let res = execute(deps.as_mut(), env.clone(), message_info(&quinn, &[]), set(profile.clone()))?;
+        assert_eq!(res.events[0].ty, "social_tip.profile");
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetProfile { identifier: "quinn".to_string() })?;
+        let found: ProfileResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(found, ProfileResponse {
+            identifier: "quinn".to_string(),
+            address: Some(quinn.clone()),
+            profile: Some(profile),
+        });
+
+        execute(deps.as_mut(), env.clone(), message_info(&quinn, &[]), ExecuteMsg::RemoveProfile {
+            identifier: "quinn".to_string(),
+        })?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetProfile { identifier: "quinn".to_string() })?;
+        let found: ProfileResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(found.profile, None);
+        assert_eq!(found.address, Some(quinn));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payout_policies() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup_contract(&mut deps);
+        let sender = deps.api.addr_make("sender");
+        let rita = deps.api.addr_make("rita");
+        let cold = deps.api.addr_make("cold_wallet");
+        let savings = deps.api.addr_make("savings");
+        let funded = message_info(&sender, &[Coin::new(1_000u128, "uxion")]);
+        execute(deps.as_mut(), env.clone(), message_info(&rita, &[]), ExecuteMsg::Register {
+            identifier: "rita".to_string(),
+            claim_pending: false,
+            referrer: None,
+        })?;
+        let set_policy = |policy: PayoutPolicyMsg| ExecuteMsg::SetPayoutPolicy { identifier: "rita".to_string(), policy };
+        let sends = |res: &Response| -> Vec<(String, Vec<Coin>)> {
+            res.messages.iter().map(|m| match &m.msg {
+                CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => (to_address.clone(), amount.clone()),
+                _ => panic!("unexpected message"),
+            }).collect()
+        };
+
+        // only the owner picks the policy
+        let err = execute(deps.as_mut(), env.clone(), funded.clone(), set_policy(PayoutPolicyMsg::Hold)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // forward everything to a cold wallet
+        execute(deps.as_mut(), env.clone(), message_info(&rita, &[]), set_policy(PayoutPolicyMsg::Forward {
+            address: cold.to_string(),
+        }))?;
+        let res = execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("rita", 1_000, None))?;
+        assert_eq!(sends(&res), vec![(cold.to_string(), vec![Coin::new(990u128, "uxion")])]);
+        assert!(res.events[0].attributes.iter().any(|a| a.key == "payout" && a.value == "forward"));
+
+        // put 20% aside
+        let err = execute(deps.as_mut(), env.clone(), message_info(&rita, &[]), set_policy(PayoutPolicyMsg::Split {
+            savings: savings.to_string(),
+            bps: 10_000,
+        })).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Savings share must be between 1 and 9999 bps").into());
+        execute(deps.as_mut(), env.clone(), message_info(&rita, &[]), set_policy(PayoutPolicyMsg::Split {
+            savings: savings.to_string(),
+            bps: 2_000,
+        }))?;
+        let res = execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("rita", 1_000, None))?;
+        assert_eq!(sends(&res), vec![
+            (rita.to_string(), vec![Coin::new(792u128, "uxion")]),
+            (savings.to_string(), vec![Coin::new(198u128, "uxion")]),
+        ]);
+
+        // a destination blocked after the policy was set is paid nothing: tips
+        // fail and micro tips already batched wait until it is unblocked
+        let admin = message_info(&deps.api.addr_make("creator"), &[]);
+        execute(deps.as_mut(), env.clone(), admin.clone(), ExecuteMsg::UpdateConfig(ConfigUpdate {
+            micro_tip_threshold: Some(Uint128::new(100)),
+            ..ConfigUpdate::default()
+        }))?;
+        execute(deps.as_mut(), env.clone(), message_info(&sender, &[Coin::new(50u128, "uxion")]), tip_msg("rita", 50, None))?;
+        let block = BlockTarget::Address(savings.to_string());
+        execute(deps.as_mut(), env.clone(), admin.clone(), ExecuteMsg::Block { target: block.clone(), reason: None })?;
+        let err = execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("rita", 1_000, None)).unwrap_err();
+        assert_eq!(err, ContractError::Blocked { target: savings.to_string() });
+        let err = execute(deps.as_mut(), env.clone(), funded.clone(), ExecuteMsg::Settle { limit: None }).unwrap_err();
+        assert_eq!(err, StdError::generic_err("No micro tips to settle").into());
+        execute(deps.as_mut(), env.clone(), admin, ExecuteMsg::Unblock { target: block })?;
+        let res = execute(deps.as_mut(), env.clone(), funded.clone(), ExecuteMsg::Settle { limit: None })?;
+        assert_eq!(sends(&res), vec![
+            (rita.to_string(), vec![Coin::new(40u128, "uxion")]),
+            (savings.to_string(), vec![Coin::new(10u128, "uxion")]),
+        ]);
+
+        // hold tips in the contract and withdraw later
+        execute(deps.as_mut(), env.clone(), message_info(&rita, &[]), set_policy(PayoutPolicyMsg::Hold))?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::PayoutPolicy { identifier: "rita".to_string() })?;
+        let policy: PayoutPolicyResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(policy.policy, PayoutPolicy::Hold);
+        for _ in 0..2 {
+            let res = execute(deps.as_mut(), env.clone(), funded.clone(), tip_msg("rita", 1_000, None))?;
+            assert!(res.messages.is_empty());
+        }
+        let balance = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| -> Vec<Coin> {
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::InternalBalance { address: rita.to_string() }).unwrap();
+            cosmwasm_std::from_json::<InternalBalanceResponse>(&res).unwrap().balances
+        };
+        assert_eq!(balance(&deps), vec![Coin::new(1_980u128, "uxion")]);
+
+        let withdraw = |amount: Option<u128>| ExecuteMsg::Withdraw { denom: "uxion".to_string(), amount: amount.map(Uint128::new) };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&rita, &[]), withdraw(Some(2_000))).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Insufficient balance: 1980uxion").into());
+        let res = execute(deps.as_mut(), env.clone(), message_info(&rita, &[]), withdraw(Some(980)))?;
+        assert_eq!(sends(&res), vec![(rita.to_string(), vec![Coin::new(980u128, "uxion")])]);
+        assert_eq!(balance(&deps), vec![Coin::new(1_000u128, "uxion")]);
+        execute(deps.as_mut(), env.clone(), message_info(&rita, &[]), withdraw(None))?;
+        assert!(balance(&deps).is_empty());
+
+        // back to direct payouts
+        execute(deps.as_mut(), env.clone(), message_info(&rita, &[]), set_policy(PayoutPolicyMsg::Direct))?;
+        let res = execute(deps.as_mut(), env.clone(), funded, tip_msg("rita", 1_000, None))?;
+        assert_eq!(sends(&res), vec![(rita.to_string(), vec![Coin::new(990u128, "uxion")])]);
+
+        Ok(())
+    }
+
+    // everything the contract owes in a denom: internal balances, pending
+    // escrows, unwithdrawn fees and unclaimed referral rewards
+    fn liabilities(app: &cw_multi_test::App, contract: &cosmwasm_std::Addr, denom: &str) -> Uint128 {
+        use crate::state::{escrows, BALANCES, FEE_POOL, MICRO_TIP_BUCKETS, REFERRAL_EARNINGS};
+        use cosmwasm_std::Order;
+
+        let storage = app.contract_storage(contract);
+        let held: Uint128 = BALANCES
+            .range(&*storage, None, None, Order::Ascending)
+            .map(|item| item.unwrap())
+            .filter(|((_, d), _)| d == denom)
+            .map(|(_, amount)| amount)
+            .sum();
+        let escrowed: Uint128 = escrows()
+            .range(&*storage, None, None, Order::Ascending)
+            .map(|item| item.unwrap().1.amount)
+            .filter(|coin| coin.denom == denom)
+            .map(|coin| coin.amount)
+            .sum();
+        let fees = FEE_POOL.may_load(&*storage, denom.to_string()).unwrap().unwrap_or_default();
+        let referrals: Uint128 = REFERRAL_EARNINGS
+            .range(&*storage, None, None, Order::Ascending)
+            .map(|item| item.unwrap())
+            .filter(|((_, d), _)| d == denom)
+            .map(|(_, earnings)| earnings.unpaid)
+            .sum();
+        let batched: Uint128 = MICRO_TIP_BUCKETS
+            .range(&*storage, None, None, Order::Ascending)
+            .map(|item| item.unwrap())
+            .filter(|((_, d), _)| d == denom)
+            .map(|(_, bucket)| bucket.gross)
+            .sum();
+        held + escrowed + fees + referrals + batched
+    }
+
+    #[test]
+    fn test_internal_ledger_matches_bank_balance() {
+        use cw_multi_test::{App, ContractWrapper, Executor};
+
+        let mut app = App::default();
+        let alice = app.api().addr_make("alice");
+        let bob = app.api().addr_make("bob");
+        let carol = app.api().addr_make("carol");
+        let creator = app.api().addr_make("creator");
+        app.init_modules(|router, _, storage| {
+            for user in [&alice, &bob, &carol] {
+                router.bank.init_balance(storage, user, vec![Coin::new(100_000u128, "uxion")]).unwrap();
+            }
+        });
+        let code_id = app.store_code(Box::new(ContractWrapper::new(execute, instantiate, query)));
+        let contract = app.instantiate_contract(code_id, creator.clone(), &InstantiateMsg {
+            admin: None,
+            guardian: None,
+            token_denom: "uxion".to_string(),
+            platform_wallet: app.api().addr_make("platform_wallet").to_string(),
+            fee_shares: vec![],
+            tip_retention: RetentionPolicy::default(),
+            rate_limits: RateLimits::default(),
+            claim_link_ttl: None,
+            max_relayer_fee: None,
+            referral: ReferralPolicy::default(),
+            micro_tip_threshold: None,
+        }, &[], "social-tip", None).unwrap();
+        let solvent = |app: &App| {
+            let bank = app.wrap().query_balance(&contract, "uxion").unwrap().amount;
+            assert_eq!(bank, liabilities(app, &contract, "uxion"));
+        };
+        let internal_tip = |identifier: &str, amount: u128| ExecuteMsg::Transfer {
+            identifier: identifier.to_string(),
+            amount: Coin::new(amount, "uxion"),
+            memo: None,
+            secret_hash: None,
+            from_balance: true,
+        };
+
+        for (user, identifier) in [(&alice, "alice"), (&bob, "bob")] {
+            app.execute_contract(user.clone(), contract.clone(), &ExecuteMsg::Register {
+                identifier: identifier.to_string(),
+                claim_pending: false,
+                referrer: None,
+            }, &[]).unwrap();
+        }
+        app.execute_contract(alice.clone(), contract.clone(), &ExecuteMsg::Deposit {}, &[Coin::new(50_000u128, "uxion")]).unwrap();
+        solvent(&app);
+
+        // tips between registered users move no funds out of the contract
+        for _ in 0..5 {
+            let res = app.execute_contract(alice.clone(), contract.clone(), &internal_tip("bob", 1_000), &[]).unwrap();
+            assert!(!res.events.iter().any(|e| e.ty == "transfer"));
+            solvent(&app);
+        }
+        let res: InternalBalanceResponse = app.wrap().query_wasm_smart(&contract, &QueryMsg::InternalBalance {
+            address: bob.to_string(),
+        }).unwrap();
+        assert_eq!(res.balances, vec![Coin::new(4_950u128, "uxion")]);
+
+        // overdrawing fails and leaves everything as it was
+        assert!(app.execute_contract(bob.clone(), contract.clone(), &internal_tip("alice", 5_000), &[]).is_err());
+        assert!(app.execute_contract(alice.clone(), contract.clone(), &internal_tip("bob", 100), &[Coin::new(100u128, "uxion")]).is_err());
+        solvent(&app);
+
+        // an internal tip to an unregistered identifier is escrowed as usual
+        app.execute_contract(alice.clone(), contract.clone(), &internal_tip("dora@mail.com", 2_000), &[]).unwrap();
+        solvent(&app);
+
+        // a funded tip from outside the ledger is paid out by bank message
+        app.execute_contract(carol.clone(), contract.clone(), &tip_msg("bob", 3_000, None), &[Coin::new(3_000u128, "uxion")]).unwrap();
+        solvent(&app);
+
+        // a tip attaching less than it sends cannot be paid out of alice's deposit
+        for funds in [vec![], vec![Coin::new(30u128, "uxion")]] {
+            let err = app.execute_contract(carol.clone(), contract.clone(), &tip_msg("bob", 3_000, None), &funds).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), "Generic error: Attach exactly 3000uxion to send this tip");
+        }
+        solvent(&app);
+
+        app.execute_contract(bob.clone(), contract.clone(), &ExecuteMsg::Withdraw {
+            denom: "uxion".to_string(),
+            amount: Some(Uint128::new(4_000)),
+        }, &[]).unwrap();
+        solvent(&app);
+        app.execute_contract(carol.clone(), contract.clone(), &ExecuteMsg::WithdrawFees {}, &[]).unwrap();
+        solvent(&app);
+
+        // alice put in 50k and paid out 7k in tips, bob received 4950 internally,
+        // 2970 by bank message and withdrew 4000
+        assert_eq!(app.wrap().query_balance(&alice, "uxion").unwrap().amount, Uint128::new(50_000));
+        assert_eq!(app.wrap().query_balance(&bob, "uxion").unwrap().amount, Uint128::new(106_970));
+        let res: InternalBalanceResponse = app.wrap().query_wasm_smart(&contract, &QueryMsg::InternalBalance {
+            address: alice.to_string(),
+        }).unwrap();
+        assert_eq!(res.balances, vec![Coin::new(43_000u128, "uxion")]);
+    }
+
+    #[test]
+    fn test_micro_tip_batching() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup_contract(&mut deps);
+        let creator = message_info(&deps.api.addr_make("creator"), &[]);
+        let sender = deps.api.addr_make("sender");
+        let rita = deps.api.addr_make("rita");
+        let sam = deps.api.addr_make("sam");
+        execute(deps.as_mut(), env.clone(), creator.clone(), ExecuteMsg::UpdateConfig(ConfigUpdate {
+            micro_tip_threshold: Some(Uint128::new(100)),
+            ..ConfigUpdate::default()
+        }))?;
+        for (who, identifier) in [(&rita, "rita"), (&sam, "sam")] {
+            execute(deps.as_mut(), env.clone(), message_info(who, &[]), ExecuteMsg::Register {
+                identifier: identifier.to_string(),
+                claim_pending: false,
+                referrer: None,
+            })?;
+        }
+
+        // tips under the threshold pay no fee and send nothing on their own
+        let small = message_info(&sender, &[Coin::new(50u128, "uxion")]);
+        for _ in 0..4 {
+            let res = execute(deps.as_mut(), env.clone(), small.clone(), tip_msg("rita", 50, None))?;
+            assert!(res.messages.is_empty());
+            assert_eq!(res.events[0].ty, "social_tip.micro_tip");
+        }
+        execute(deps.as_mut(), env.clone(), small.clone(), tip_msg("sam", 50, None))?;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::PendingMicroTips { identifier: "rita".to_string() })?;
+        let pending: MicroTipsResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(pending.pending, vec![PendingMicroTips {
+            denom: "uxion".to_string(),
+            gross: Uint128::new(200),
+            tips: 4,
+        }]);
+        let received = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::TipsReceived { identifier: "rita".to_string(), start_after: None, limit: None });
+            cosmwasm_std::from_json::<TipsResponse>(&res.unwrap()).map(|r| r.tips)
+        };
+        assert!(received(&deps)?.iter().all(|t| t.status == TipStatus::Batched && t.fee.is_zero()));
+
+        // tips at the threshold still go straight through
+        let res = execute(deps.as_mut(), env.clone(), message_info(&sender, &[Coin::new(100u128, "uxion")]), tip_msg("rita", 100, None))?;
+        assert_eq!(res.messages.len(), 1);
+
+        // anyone can settle; the limit caps the buckets paid per call, and
+        // 50 alone rounds the fee down to zero
+        let res = execute(deps.as_mut(), env.clone(), small.clone(), ExecuteMsg::Settle { limit: Some(1) })?;
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: sam.to_string(),
+            amount: vec![Coin::new(50u128, "uxion")],
+        }));
+
+        // the four batched tips pay a fee on their 200 total
+        let res = execute(deps.as_mut(), env.clone(), small.clone(), ExecuteMsg::Settle { limit: None })?;
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: rita.to_string(),
+            amount: vec![Coin::new(198u128, "uxion")],
+        }));
+        let settle = res.events.iter().find(|e| e.ty == "social_tip.settle").unwrap();
+        assert!(settle.attributes.iter().any(|a| a.key == "fee" && a.value == "2"));
+        let err = execute(deps.as_mut(), env.clone(), small.clone(), ExecuteMsg::Settle { limit: None }).unwrap_err();
+        assert_eq!(err, StdError::generic_err("No micro tips to settle").into());
+
+        // the batched records now carry their share of that fee
+        let batched: Vec<_> = received(&deps)?.into_iter().filter(|t| t.gross == Uint128::new(50)).collect();
+        assert_eq!(batched.len(), 4);
+        assert!(batched.iter().all(|t| t.status == TipStatus::Settled && t.net == t.gross - t.fee));
+        assert_eq!(batched.iter().map(|t| t.fee).sum::<Uint128>(), Uint128::new(2));
+
+        // the batch fee lands in the pool next to the regular tip's fee
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::FeePool {})?;
+        let pool: FeePoolResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(pool.accrued, vec![Coin::new(3u128, "uxion")]);
+
+        // every batched tip counts for rita, not just the settlement
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::TopRecipients { denom: "uxion".to_string(), limit: Some(1) })?;
+        let top: LeaderboardResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(top.entries, vec![LeaderboardEntry {
+            account: "rita".to_string(),
+            amount: Uint128::from(99u128 + 198),
+            count: 5,
+        }]);
+
+        // a full bucket stops batching until it is settled
+        for _ in 0..30 {
+            execute(deps.as_mut(), env.clone(), small.clone(), tip_msg("sam", 50, None))?;
+        }
+        let res = execute(deps.as_mut(), env.clone(), small.clone(), tip_msg("sam", 50, None))?;
+        assert_eq!(res.messages.len(), 1);
+        execute(deps.as_mut(), env.clone(), small.clone(), ExecuteMsg::Settle { limit: None })?;
+        let res = execute(deps.as_mut(), env.clone(), small, tip_msg("sam", 50, None))?;
+        assert!(res.messages.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_transfer_from() -> Result<(), ContractError> {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        setup_contract(&mut deps);
+        let alice = deps.api.addr_make("alice");
+        let bot = deps.api.addr_make("bot");
+        let rita = deps.api.addr_make("rita");
+        let stranger = deps.api.addr_make("stranger");
+        execute(deps.as_mut(), env.clone(), message_info(&rita, &[]), ExecuteMsg::Register {
+            identifier: "rita".to_string(),
+            claim_pending: false,
+            referrer: None,
+        })?;
+        execute(deps.as_mut(), env.clone(), message_info(&alice, &[Coin::new(1_000u128, "uxion")]), ExecuteMsg::Deposit {})?;
+        let approve = ExecuteMsg::Approve {
+            spender: bot.to_string(),
+            denom: "uxion".to_string(),
+            amount: Uint128::new(500),
+            per_tip_cap: Some(Uint128::new(200)),
+            expires: Some(env.block.time.plus_seconds(3_600)),
+        };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&bot, &[]), ExecuteMsg::Approve {
+            spender: bot.to_string(),
+            denom: "uxion".to_string(),
+            amount: Uint128::new(500),
+            per_tip_cap: None,
+            expires: None,
+        }).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Cannot approve yourself").into());
+        execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), approve)?;
+
+        let tip_from = |identifier: &str, amount: u128| ExecuteMsg::TransferFrom {
+            owner: alice.to_string(),
+            identifier: identifier.to_string(),
+            amount: Coin::new(amount, "uxion"),
+            memo: None,
+            secret_hash: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&stranger, &[]), tip_from("rita", 100)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), env.clone(), message_info(&bot, &[]), tip_from("rita", 300)).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Tip exceeds the per-tip cap").into());
+
+        // the tip comes out of alice's balance and is routed like her own
+        let res = execute(deps.as_mut(), env.clone(), message_info(&bot, &[]), tip_from("rita", 200))?;
+        assert!(res.messages.is_empty());
+        let transfer = res.events.iter().find(|e| e.ty == "social_tip.transfer").unwrap();
+        assert!(transfer.attributes.iter().any(|a| a.key == "sender" && a.value == alice.as_str()));
+        let spent = res.events.iter().find(|e| e.ty == "social_tip.transfer_from").unwrap();
+        assert!(spent.attributes.iter().any(|a| a.key == "remaining" && a.value == "300"));
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::InternalBalance { address: rita.to_string() })?;
+        let balance: InternalBalanceResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(balance.balances, vec![Coin::new(198u128, "uxion")]);
+
+        let res = execute(deps.as_mut(), env.clone(), message_info(&bot, &[]), tip_from("unregistered", 200))?;
+        assert!(res.events.iter().any(|e| e.ty == "social_tip.escrow"));
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Allowances { owner: alice.to_string(), start_after: None, limit: None })?;
+        let allowances: AllowancesResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(allowances.allowances, vec![AllowanceInfo {
+            spender: bot.to_string(),
+            denom: "uxion".to_string(),
+            remaining: Uint128::new(100),
+            per_tip_cap: Some(Uint128::new(200)),
+            expires: Some(env.block.time.plus_seconds(3_600)),
+        }]);
+
+        // allowances are listed a page at a time by (spender, denom)
+        execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), ExecuteMsg::Approve {
+            spender: bot.to_string(),
+            denom: "uatom".to_string(),
+            amount: Uint128::new(50),
+            per_tip_cap: None,
+            expires: None,
+        })?;
+        let page = |start_after: Option<(String, String)>| {
+            let res = query(deps.as_ref(), env.clone(), QueryMsg::Allowances { owner: alice.to_string(), start_after, limit: Some(1) });
+            cosmwasm_std::from_json::<AllowancesResponse>(&res.unwrap()).map(|r| r.allowances)
+        };
+        let first = page(None)?;
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].denom, "uatom");
+        let second = page(Some((bot.to_string(), "uatom".to_string())))?;
+        assert_eq!(second, allowances.allowances);
+        assert!(page(Some((bot.to_string(), "uxion".to_string())))?.is_empty());
+        let err = execute(deps.as_mut(), env.clone(), message_info(&bot, &[]), tip_from("rita", 200)).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Allowance exceeded").into());
+
+        // the allowance cannot outlive its expiry or a revoke
+        env.block.time = env.block.time.plus_seconds(3_600);
+        let err = execute(deps.as_mut(), env.clone(), message_info(&bot, &[]), tip_from("rita", 50)).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Allowance expired").into());
+        execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), ExecuteMsg::Revoke {
+            spender: bot.to_string(),
+            denom: "uxion".to_string(),
+        })?;
+        let err = execute(deps.as_mut(), env.clone(), message_info(&bot, &[]), tip_from("rita", 50)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // failed tips leave alice's balance as it was: 1000 - 200 - 200
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::InternalBalance { address: alice.to_string() })?;
+        let balance: InternalBalanceResponse = cosmwasm_std::from_json(&res)?;
+        assert_eq!(balance.balances, vec![Coin::new(600u128, "uxion")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_governance_sudo() {
+        use cw_multi_test::{App, ContractWrapper, Executor};
+
+        let mut app = App::default();
+        let admin = app.api().addr_make("admin");
+        let new_admin = app.api().addr_make("new_admin");
+        let alice = app.api().addr_make("alice");
+        app.init_modules(|router, _, storage| {
+            router.bank.init_balance(storage, &alice, vec![Coin::new(10_000u128, "uxion")]).unwrap();
+        });
+        let code_id = app.store_code(Box::new(ContractWrapper::new(execute, instantiate, query).with_sudo(sudo)));
+        let contract = app.instantiate_contract(code_id, admin.clone(), &InstantiateMsg {
+            admin: None,
+            guardian: None,
+            token_denom: "uxion".to_string(),
+            platform_wallet: app.api().addr_make("platform_wallet").to_string(),
+            fee_shares: vec![],
+            tip_retention: RetentionPolicy::default(),
+            rate_limits: RateLimits::default(),
+            claim_link_ttl: None,
+            max_relayer_fee: None,
+            referral: ReferralPolicy::default(),
+            micro_tip_threshold: None,
+        }, &[], "social-tip", None).unwrap();
+
+        let res = app.execute_contract(alice.clone(), contract.clone(), &tip_msg("lost", 1_000, None), &[Coin::new(1_000u128, "uxion")]).unwrap();
+        let tip_id: u64 = res.events.iter()
+            .find(|e| e.ty == "wasm-social_tip.escrow").unwrap()
+            .attributes.iter().find(|a| a.key == "tip_id").unwrap()
+            .value.parse().unwrap();
+        app.execute_contract(admin.clone(), contract.clone(), &ExecuteMsg::Pause {
+            operations: vec![Operation::Claim, Operation::Refund],
+        }, &[]).unwrap();
+
+        // the admin key is gone: governance names a new admin
+        let err = app.wasm_sudo(contract.clone(), &SudoMsg::UpdateConfig(ConfigUpdate {
+            claim_link_ttl: Some(0),
+            ..ConfigUpdate::default()
+        })).unwrap_err();
+        assert!(err.root_cause().to_string().contains("Claim link TTL must be positive"));
+        app.wasm_sudo(contract.clone(), &SudoMsg::UpdateConfig(ConfigUpdate {
+            admin: Some(new_admin.to_string()),
+            ..ConfigUpdate::default()
+        })).unwrap();
+        let config: Config = app.wrap().query_wasm_smart(&contract, &QueryMsg::Config {}).unwrap();
+        assert_eq!(config.admin, new_admin);
+        let err = app.execute_contract(admin.clone(), contract.clone(), &ExecuteMsg::Unpause {
+            operations: vec![Operation::Claim],
+        }, &[]).unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::Unauthorized {});
+
+        let res = app.wasm_sudo(contract.clone(), &SudoMsg::Unpause {
+            operations: vec![Operation::Claim, Operation::Refund],
+        }).unwrap();
+        assert!(res.events.iter().any(|e| e.ty == "wasm-social_tip.unpause"
+            && e.attributes.iter().any(|a| a.key == "sender" && a.value == "governance")));
+        let pause: PauseResponse = app.wrap().query_wasm_smart(&contract, &QueryMsg::PauseStatus {}).unwrap();
+        assert_eq!(pause.paused, PauseState::default());
+
+        // specific escrows go back to their senders, without blocking the identifier
+        assert!(app.wasm_sudo(contract.clone(), &SudoMsg::ForceRefund { escrow_ids: vec![tip_id + 1] }).is_err());
+        app.wasm_sudo(contract.clone(), &SudoMsg::ForceRefund { escrow_ids: vec![tip_id, tip_id] }).unwrap();
+        assert_eq!(app.wrap().query_balance(&alice, "uxion").unwrap().amount, Uint128::new(9_990));
+        assert!(app.wasm_sudo(contract.clone(), &SudoMsg::ForceRefund { escrow_ids: vec![tip_id] }).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use cosmwasm_std::{Addr, Coin, Env, Order, StdResult, Storage, Uint128};
+
+use crate::state::{tips, RetentionPolicy, Tip, TipStatus, TIP_COUNT, TIP_RECORDS};
+
+// max number of old records looked at for pruning on each new tip
+const PRUNE_SCAN_LIMIT: usize = 10;
+
+// append a new tip to the history ledger and return its id
+#[allow(clippy::too_many_arguments)]
+pub fn record_tip(
+    storage: &mut dyn Storage,
+    env: &Env,
+    retention: &RetentionPolicy,
+    sender: &Addr,
+    recipient: &str,
+    gross: &Coin,
+    fee: Uint128,
+    net: Uint128,
+    memo: Option<String>,
+    status: TipStatus,
+) -> StdResult<u64> {
+    prune_tips(storage, env, retention)?;
+
+    let id = TIP_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    TIP_COUNT.save(storage, &id)?;
+    let tip = Tip {
+        id,
+        sender: sender.clone(),
+        recipient: recipient.to_string(),
+        gross: gross.amount,
+        fee,
+        net,
+        denom: gross.denom.clone(),
+        time: env.block.time,
+        memo,
+        status,
+    };
+    tips().save(storage, id, &tip)?;
+    let records = TIP_RECORDS.may_load(storage)?.unwrap_or_default();
+    TIP_RECORDS.save(storage, &(records + 1))?;
+    Ok(id)
+}
+
+// move a recorded tip to a new status (no-op if the record was pruned)
+pub fn set_tip_status(storage: &mut dyn Storage, id: u64, status: TipStatus) -> StdResult<()> {
+    if let Some(mut tip) = tips().may_load(storage, id)? {
+        tip.status = status;
+        tips().save(storage, id, &tip)?;
+    }
+    Ok(())
+}
+
+// Mark the batched tips of a settled bucket as Settled and split the bucket's
+// fee over them by amount. Cumulative rounding makes the shares add up to the
+// fee exactly without any of them exceeding its tip.
+pub fn settle_batched(storage: &mut dyn Storage, tip_ids: &[u64], gross: Uint128, fee: Uint128) -> StdResult<()> {
+    let mut counted = Uint128::zero();
+    let mut charged = Uint128::zero();
+    for id in tip_ids {
+        let Some(mut tip) = tips().may_load(storage, *id)? else {
+            continue;
+        };
+        counted += tip.gross;
+        let share = fee.multiply_ratio(counted, gross) - charged;
+        charged += share;
+        tip.fee = share;
+        tip.net = tip.gross - share;
+        tip.status = TipStatus::Settled;
+        tips().save(storage, *id, &tip)?;
+    }
+    Ok(())
+}
+
+// drop the oldest settled records that fall outside the retention policy.
+// escrowed and batched tips are never pruned since an escrow or bucket still
+// refers to them, so only the settled part of the index is read and they
+// cannot stall it.
+fn prune_tips(storage: &mut dyn Storage, env: &Env, retention: &RetentionPolicy) -> StdResult<()> {
+    if retention.max_records.is_none() && retention.max_age.is_none() {
+        return Ok(());
+    }
+    let oldest: Vec<Tip> = tips()
+        .idx
+        .unsettled
+        .prefix(0)
+        .range(storage, None, None, Order::Ascending)
+        .take(PRUNE_SCAN_LIMIT)
+        .map(|item| item.map(|(_, tip)| tip))
+        .collect::<StdResult<_>>()?;
+
+    // leave room for the record about to be added
+    let mut records = TIP_RECORDS.may_load(storage)?.unwrap_or_default();
+    for tip in oldest {
+        let over_count = retention.max_records.is_some_and(|max| records >= max);
+        let too_old = retention
+            .max_age
+            .is_some_and(|age| tip.time.plus_seconds(age) < env.block.time);
+        if over_count || too_old {
+            tips().remove(storage, tip.id)?;
+            records -= 1;
+        }
+    }
+    TIP_RECORDS.save(storage, &records)
+}
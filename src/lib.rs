@@ -1,8 +1,18 @@
 pub mod contract;
 mod error;
+pub mod events;
+pub mod fees;
 pub mod helpers;
 pub mod integration_tests;
+pub mod ledger;
 pub mod msg;
+pub mod multitest;
+pub mod notification;
+pub mod payout;
+pub mod rate_limit;
+pub mod relay;
+pub mod snapshot;
 pub mod state;
+pub mod stats;
 
 pub use crate::error::ContractError;
@@ -1,21 +1,221 @@
-use cosmwasm_std::{Addr, Coin};
+use cosmwasm_std::{Addr, Binary, Coin, Timestamp, Uint128};
 use schemars::JsonSchema;
 use serde::{Serialize, Deserialize};
-use crate::state::Escrow;
+use crate::state::{BlockEntry, Config, Escrow, FeeShare, Operation, PayoutPolicy, Profile, Referral, ReferralPolicy, PauseState, RateLimits, RetentionPolicy, Tip};
 use cosmwasm_schema::QueryResponses;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
+    // defaults to the instantiating address
+    pub admin: Option<String>,
+    pub guardian: Option<String>,
     pub token_denom: String,
     pub platform_wallet: String,
+    // split of the platform fee across wallets, 100% to platform_wallet if empty
+    #[serde(default)]
+    pub fee_shares: Vec<FeeShareMsg>,
+    // pruning policy for the tip history ledger (keep everything by default)
+    #[serde(default)]
+    pub tip_retention: RetentionPolicy,
+    // minimum tips and escrow rate limits (none by default)
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    // lifetime of claim links in seconds, 30 days if unset
+    pub claim_link_ttl: Option<u64>,
+    // cap on relayer reimbursement for relayed claims, none if unset
+    pub max_relayer_fee: Option<Uint128>,
+    // referral rewards, off by default
+    #[serde(default)]
+    pub referral: ReferralPolicy,
+    // tips below this to registered identifiers are batched, off if unset
+    pub micro_tip_threshold: Option<Uint128>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    Register { identifier: String }, // register username/email
-    Transfer { identifier: String, amount: Coin }, // Transfer token
-    Claim { identifier: String }, // Claim escrowed tokens
+    // register username/email. With `claim_pending` any escrowed tips for it
+    // are delivered in the same transaction (up to 30, the rest stay claimable).
+    Register {
+        identifier: String,
+        #[serde(default)]
+        claim_pending: bool,
+        // registered identifier that invited this one
+        referrer: Option<String>,
+    },
+    // Transfer token. `secret_hash` (hex sha256) lets whoever holds the secret
+    // claim the escrow with ClaimWithSecret; such an escrow is only released by
+    // its secret, not by registering the identifier. With `from_balance` the tip is
+    // paid from the sender's internal balance, and a registered recipient is
+    // credited internally instead of being sent a bank message.
+    Transfer {
+        identifier: String,
+        amount: Coin,
+        memo: Option<String>,
+        secret_hash: Option<String>,
+        #[serde(default)]
+        from_balance: bool,
+    },
+    // Move the attached funds into the sender's internal balance.
+    Deposit {},
+    // Pay out up to `limit` pending micro-tip buckets. Anyone may call it.
+    Settle { limit: Option<u32> },
+    // Claim escrowed tokens, to the caller unless a destination is given.
+    // `denom` limits the claim to one denom and `amount` claims only part of
+    // it, leaving the rest in escrow.
+    Claim {
+        identifier: String,
+        destination: Option<String>,
+        denom: Option<String>,
+        amount: Option<Uint128>,
+    },
+    // First half of a claim by secret: commit to claim_reveal_hash(secret,
+    // recipient) without revealing the secret.
+    CommitClaim { escrow_id: u64, commitment: String },
+    // Claim one escrow by revealing its secret, paying `recipient` (the caller
+    // by default) and optionally registering the identifier to it. Needs a
+    // CommitClaim for this secret and recipient from an earlier block.
+    ClaimWithSecret {
+        escrow_id: u64,
+        secret: String,
+        recipient: Option<String>,
+        #[serde(default)]
+        register: bool,
+    },
+    Refund { identifier: String }, // Return unclaimed escrow to its sender
+    // Relayer-submitted claim signed off-chain by the recipient
+    RelayClaim {
+        payload: ClaimPayload,
+        scheme: SignatureScheme,
+        pubkey: Binary,
+        signature: Binary,
+    },
+    // Pay the accrued fees out to the fee wallets. Anyone may call it.
+    WithdrawFees {},
+    // Owner only: replace the profile shown for an identifier.
+    SetProfile { identifier: String, profile: Profile },
+    // Owner only: remove the profile of an identifier.
+    RemoveProfile { identifier: String },
+    // Owner only: choose how tips to an identifier are paid out.
+    SetPayoutPolicy { identifier: String, policy: PayoutPolicyMsg },
+    // Let `spender` tip up to `amount` out of the sender's internal balance.
+    // Replaces any earlier allowance for the same spender and denom.
+    Approve {
+        spender: String,
+        denom: String,
+        amount: Uint128,
+        per_tip_cap: Option<Uint128>,
+        expires: Option<Timestamp>,
+    },
+    Revoke { spender: String, denom: String },
+    // Tip on the owner's behalf from their internal balance, within the
+    // allowance they granted the sender. Routed like any other Transfer.
+    TransferFrom {
+        owner: String,
+        identifier: String,
+        amount: Coin,
+        memo: Option<String>,
+        secret_hash: Option<String>,
+    },
+    // Withdraw from the sender's internal balance, all of it if no amount is given.
+    Withdraw { denom: String, amount: Option<Uint128> },
+    // Pay out the referral rewards earned by one of the sender's identifiers.
+    ClaimReferralRewards { identifier: String },
+    // Admin only: replace the fee split.
+    SetFeeShares { shares: Vec<FeeShareMsg> },
+    // Admin only: change settings, unset fields are left as they are.
+    UpdateConfig(ConfigUpdate),
+    // Admin or guardian: switch operations off. The guardian cannot pause claim
+    // or refund, so user funds stay withdrawable unless the admin decides otherwise.
+    Pause { operations: Vec<Operation> },
+    // Admin only: switch operations back on
+    Unpause { operations: Vec<Operation> },
+    // Admin only: add an address or identifier to the denylist
+    Block { target: BlockTarget, reason: Option<String> },
+    // Admin only: remove an address or identifier from the denylist
+    Unblock { target: BlockTarget },
+    // Admin only: return the frozen escrow of a blocked identifier to its sender
+    ForceRefund { identifier: String },
+}
+
+// What a recipient signs to have a relayer claim for them. Field order is
+// part of the signed bytes, see relay::sign_bytes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimPayload {
+    pub chain_id: String,
+    pub contract: String,
+    pub identifier: String,
+    pub nonce: u64,
+    // where the funds go, the signer's address by default
+    pub destination: Option<String>,
+    // only this relayer may submit the claim, if set
+    pub relayer: Option<String>,
+    // taken from the claim and paid to the relayer
+    pub relayer_fee: Uint128,
+}
+
+// PayoutPolicy as given in messages, with unchecked addresses
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutPolicyMsg {
+    Direct,
+    Forward { address: String },
+    Split { savings: String, bps: u16 },
+    Hold,
+}
+
+// one wallet's cut of the platform fee as given in messages
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeShareMsg {
+    pub address: String,
+    pub bps: u16,
+}
+
+// settings the admin may change after instantiation; the denom is fixed
+// because escrows and stats are kept in it
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ConfigUpdate {
+    pub admin: Option<String>,
+    pub guardian: Option<String>,
+    // also moves the fee share if the wallet had all of it, otherwise fees
+    // keep going where fee_shares says
+    pub platform_wallet: Option<String>,
+    pub fee_shares: Option<Vec<FeeShareMsg>>,
+    pub tip_retention: Option<RetentionPolicy>,
+    pub rate_limits: Option<RateLimits>,
+    pub claim_link_ttl: Option<u64>,
+    pub max_relayer_fee: Option<Uint128>,
+    pub referral: Option<ReferralPolicy>,
+    pub micro_tip_threshold: Option<Uint128>,
+}
+
+// Sent by chain governance only, never by an account.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
+pub enum SudoMsg {
+    // apply a config change without the admin, e.g. to replace a lost admin key
+    UpdateConfig(ConfigUpdate),
+    Unpause { operations: Vec<Operation> },
+    // refund these escrows to their senders, blocked identifier or not
+    ForceRefund { escrow_ids: Vec<u64> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureScheme {
+    Secp256k1,
+    Ed25519,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockTarget {
+    Address(String),
+    Identifier(String),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, QueryResponses)]
@@ -23,10 +223,78 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     #[returns(BalanceResponse)]
     GetBalance { address: String },
+    // oldest pending escrow for the identifier
     #[returns(EscrowResponse)]
     GetEscrow { identifier: String },
+    #[returns(EscrowsResponse)]
+    GetEscrows { identifier: String, start_after: Option<u64>, limit: Option<u32> },
+    #[returns(EscrowResponse)]
+    GetEscrowById { escrow_id: u64 },
     #[returns(AccountResponse)]
     GetAccount { identifier: String },
+    #[returns(TipResponse)]
+    GetTip { id: u64 },
+    // tips sent by an address, most recent first
+    #[returns(TipsResponse)]
+    TipsSent { sender: String, start_after: Option<u64>, limit: Option<u32> },
+    // tips sent to an identifier, most recent first
+    #[returns(TipsResponse)]
+    TipsReceived { identifier: String, start_after: Option<u64>, limit: Option<u32> },
+    // nonce the next relayed claim signed by this address must use
+    #[returns(RelayNonceResponse)]
+    RelayNonce { address: String },
+    // who referred an identifier and until when they earn from it
+    #[returns(ReferralResponse)]
+    GetReferral { identifier: String },
+    // rewards earned by a referrer, per denom
+    #[returns(ReferralEarningsResponse)]
+    ReferralEarnings { identifier: String },
+    #[returns(PayoutPolicyResponse)]
+    PayoutPolicy { identifier: String },
+    // micro tips waiting for Settle, per denom
+    #[returns(MicroTipsResponse)]
+    PendingMicroTips { identifier: String },
+    // what the contract holds for an address, per denom
+    #[returns(InternalBalanceResponse)]
+    InternalBalance { address: String },
+    // allowances an owner has granted, by spender and denom; `start_after`
+    // is the (spender, denom) of the last allowance already returned
+    #[returns(AllowancesResponse)]
+    Allowances { owner: String, start_after: Option<(String, String)>, limit: Option<u32> },
+    // address and profile behind an identifier
+    #[returns(ProfileResponse)]
+    GetProfile { identifier: String },
+    #[returns(Config)]
+    Config {},
+    // Contract state in the versioned snapshot format, for audits and
+    // migration dry runs: `snapshot` returns the config and other single
+    // items, `snapshot_page` each stored map a page at a time. Query them all
+    // at one height and check or diff the result with the snapshot tool.
+    #[returns(crate::snapshot::SnapshotHeader)]
+    Snapshot {},
+    #[returns(crate::snapshot::SnapshotPage)]
+    SnapshotPage {
+        section: crate::snapshot::SnapshotSection,
+        // `next` of the previous page
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    },
+    // fees waiting for WithdrawFees and how they will be split
+    #[returns(FeePoolResponse)]
+    FeePool {},
+    #[returns(PauseResponse)]
+    PauseStatus {},
+    #[returns(BlockedResponse)]
+    IsBlocked { target: BlockTarget },
+    // total tip volume and count for a denom
+    #[returns(StatsResponse)]
+    Stats { denom: String },
+    // identifiers that received the most, by net amount
+    #[returns(LeaderboardResponse)]
+    TopRecipients { denom: String, limit: Option<u32> },
+    // addresses that sent the most, by gross amount
+    #[returns(LeaderboardResponse)]
+    TopSenders { denom: String, limit: Option<u32> },
 }
 
 // We define a custom struct for each query response
@@ -41,7 +309,126 @@ pub struct EscrowResponse {
     pub escrow: Option<Escrow>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EscrowsResponse {
+    pub escrows: Vec<Escrow>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AccountResponse {
     pub address: Option<Addr>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TipResponse {
+    pub tip: Option<Tip>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TipsResponse {
+    pub tips: Vec<Tip>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatsResponse {
+    pub denom: String,
+    pub volume: Uint128,
+    pub tips: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardEntry {
+    // identifier for recipients, address for senders
+    pub account: String,
+    pub amount: Uint128,
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardResponse {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PauseResponse {
+    pub paused: PauseState,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BlockedResponse {
+    pub entry: Option<BlockEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RelayNonceResponse {
+    pub nonce: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeePoolResponse {
+    pub accrued: Vec<Coin>,
+    pub shares: Vec<FeeShare>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralResponse {
+    pub referral: Option<Referral>,
+    // end of the reward period, None if there is no referral
+    pub rewards_until: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralEarning {
+    pub denom: String,
+    pub earned: Uint128,
+    pub unpaid: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralEarningsResponse {
+    pub earnings: Vec<ReferralEarning>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProfileResponse {
+    pub identifier: String,
+    // None if the identifier is not registered
+    pub address: Option<Addr>,
+    pub profile: Option<Profile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PayoutPolicyResponse {
+    pub policy: PayoutPolicy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InternalBalanceResponse {
+    pub balances: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingMicroTips {
+    pub denom: String,
+    pub gross: Uint128,
+    pub tips: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MicroTipsResponse {
+    pub pending: Vec<PendingMicroTips>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllowanceInfo {
+    pub spender: String,
+    pub denom: String,
+    pub remaining: Uint128,
+    pub per_tip_cap: Option<Uint128>,
+    pub expires: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllowancesResponse {
+    pub allowances: Vec<AllowanceInfo>,
+}
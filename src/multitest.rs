@@ -0,0 +1,496 @@
+#[allow(clippy::module_inception)]
+#[cfg(test)]
+mod multitest {
+    use cosmwasm_std::{from_json, to_json_vec, Addr, Coin, CosmosMsg, WasmMsg};
+    use cw_multi_test::error::AnyResult;
+    use cw_multi_test::{App, AppResponse, ContractWrapper, Executor};
+
+    use crate::contract::{execute, instantiate, query, sudo};
+    use crate::helpers::SocialTipContract;
+    use crate::msg::{ExecuteMsg, EscrowsResponse, FeePoolResponse, InstantiateMsg, QueryMsg};
+    use crate::snapshot::{assemble, check, diff, fetch, Snapshot, SnapshotSection, SNAPSHOT_FORMAT};
+    use crate::state::{Escrow, RateLimits, ReferralPolicy, RetentionPolicy};
+
+    const DENOM: &str = "uxion";
+    // every funded account starts with this much of DENOM
+    const START: u128 = 100_000;
+
+    // The contract on an App with a real bank module, so every BankMsg it
+    // returns is actually executed and balances can be checked end to end.
+    struct Suite {
+        app: App,
+        contract: Addr,
+        platform_wallet: Addr,
+    }
+
+    impl Suite {
+        // `funded` accounts get START uxion and 1000 uatom
+        fn new(funded: &[&str]) -> Self {
+            let mut app = App::default();
+            let accounts: Vec<Addr> = funded.iter().map(|name| app.api().addr_make(name)).collect();
+            app.init_modules(|router, _, storage| {
+                for account in &accounts {
+                    router
+                        .bank
+                        .init_balance(storage, account, vec![Coin::new(1_000u128, "uatom"), Coin::new(START, DENOM)])
+                        .unwrap();
+                }
+            });
+            let code_id = app.store_code(Box::new(ContractWrapper::new(execute, instantiate, query).with_sudo(sudo)));
+            let admin = app.api().addr_make("admin");
+            let platform_wallet = app.api().addr_make("platform_wallet");
+            let contract = app
+                .instantiate_contract(code_id, admin, &InstantiateMsg {
+                    admin: None,
+                    guardian: None,
+                    token_denom: DENOM.to_string(),
+                    platform_wallet: platform_wallet.to_string(),
+                    fee_shares: vec![],
+                    tip_retention: RetentionPolicy::default(),
+                    rate_limits: RateLimits::default(),
+                    claim_link_ttl: None,
+                    max_relayer_fee: None,
+                    referral: ReferralPolicy::default(),
+                    micro_tip_threshold: None,
+                }, &[], "social-tip", None)
+                .unwrap();
+            Suite { app, contract, platform_wallet }
+        }
+
+        fn addr(&self, name: &str) -> Addr {
+            self.app.api().addr_make(name)
+        }
+
+        fn execute(&mut self, user: &str, msg: ExecuteMsg, funds: &[Coin]) -> AnyResult<AppResponse> {
+            let sender = self.addr(user);
+            let contract = self.contract.clone();
+            self.app.execute_contract(sender, contract, &msg, funds)
+        }
+
+        fn register(&mut self, user: &str, identifier: &str) -> AnyResult<AppResponse> {
+            self.execute(user, ExecuteMsg::Register {
+                identifier: identifier.to_string(),
+                claim_pending: false,
+                referrer: None,
+            }, &[])
+        }
+
+        // tip `amount` uxion, attaching exactly that
+        fn tip(&mut self, user: &str, identifier: &str, amount: u128) -> AnyResult<AppResponse> {
+            self.execute(user, transfer(identifier, amount), &[Coin::new(amount, DENOM)])
+        }
+
+        fn claim(&mut self, user: &str, identifier: &str) -> AnyResult<AppResponse> {
+            self.execute(user, ExecuteMsg::Claim {
+                identifier: identifier.to_string(),
+                destination: None,
+                denom: None,
+                amount: None,
+            }, &[])
+        }
+
+        fn refund(&mut self, user: &str, identifier: &str) -> AnyResult<AppResponse> {
+            self.execute(user, ExecuteMsg::Refund { identifier: identifier.to_string() }, &[])
+        }
+
+        fn withdraw_fees(&mut self) -> AnyResult<AppResponse> {
+            self.execute("anyone", ExecuteMsg::WithdrawFees {}, &[])
+        }
+
+        fn balance(&self, addr: &Addr) -> u128 {
+            self.app.wrap().query_balance(addr, DENOM).unwrap().amount.u128()
+        }
+
+        fn balance_of(&self, user: &str) -> u128 {
+            self.balance(&self.addr(user))
+        }
+
+        fn fee_pool(&self) -> u128 {
+            let pool: FeePoolResponse = self.app.wrap().query_wasm_smart(&self.contract, &QueryMsg::FeePool {}).unwrap();
+            pool.accrued.iter().filter(|c| c.denom == DENOM).map(|c| c.amount.u128()).sum()
+        }
+
+        // every escrow held for an identifier, across all pages
+        fn all_escrows(&self, identifier: &str) -> Vec<Escrow> {
+            let mut all: Vec<Escrow> = vec![];
+            loop {
+                let page: EscrowsResponse = self
+                    .app
+                    .wrap()
+                    .query_wasm_smart(&self.contract, &QueryMsg::GetEscrows {
+                        identifier: identifier.to_string(),
+                        start_after: all.last().map(|e| e.tip_id),
+                        limit: Some(30),
+                    })
+                    .unwrap();
+                if page.escrows.is_empty() {
+                    return all;
+                }
+                all.extend(page.escrows);
+            }
+        }
+
+        fn escrows(&self, identifier: &str) -> EscrowsResponse {
+            self.app
+                .wrap()
+                .query_wasm_smart(&self.contract, &QueryMsg::GetEscrows {
+                    identifier: identifier.to_string(),
+                    start_after: None,
+                    limit: None,
+                })
+                .unwrap()
+        }
+    }
+
+    fn transfer(identifier: &str, amount: u128) -> ExecuteMsg {
+        ExecuteMsg::Transfer {
+            identifier: identifier.to_string(),
+            amount: Coin::new(amount, DENOM),
+            memo: None,
+            secret_hash: None,
+            from_balance: false,
+        }
+    }
+
+    fn error(res: AnyResult<AppResponse>) -> String {
+        res.unwrap_err().root_cause().to_string()
+    }
+
+    #[test]
+    fn tip_to_registered_user_pays_out_net_and_pools_the_fee() {
+        let mut suite = Suite::new(&["alice"]);
+        suite.register("bob", "bob").unwrap();
+
+        suite.tip("alice", "bob", 1_000).unwrap();
+        assert_eq!(suite.balance_of("alice"), START - 1_000);
+        assert_eq!(suite.balance_of("bob"), 990);
+        assert_eq!(suite.balance(&suite.contract), 10);
+        assert_eq!(suite.balance(&suite.platform_wallet), 0);
+
+        suite.withdraw_fees().unwrap();
+        assert_eq!(suite.balance(&suite.platform_wallet), 10);
+        assert_eq!(suite.balance(&suite.contract), 0);
+    }
+
+    #[test]
+    fn escrowed_tip_is_held_until_claimed() {
+        let mut suite = Suite::new(&["alice"]);
+
+        suite.tip("alice", "carol@mail.com", 1_000).unwrap();
+        assert_eq!(suite.balance_of("alice"), START - 1_000);
+        assert_eq!(suite.balance(&suite.contract), 1_000);
+
+        // nobody can claim before registering
+        assert!(suite.claim("carol", "carol@mail.com").is_err());
+        suite.register("carol", "carol@mail.com").unwrap();
+        suite.claim("carol", "carol@mail.com").unwrap();
+        assert_eq!(suite.balance_of("carol"), 990);
+        assert!(suite.escrows("carol@mail.com").escrows.is_empty());
+
+        suite.withdraw_fees().unwrap();
+        assert_eq!(suite.balance(&suite.platform_wallet), 10);
+        assert_eq!(suite.balance(&suite.contract), 0);
+    }
+
+    #[test]
+    fn refund_returns_the_escrow_but_not_the_fee() {
+        let mut suite = Suite::new(&["alice"]);
+        suite.tip("alice", "dave@mail.com", 1_000).unwrap();
+
+        assert!(suite.refund("mallory", "dave@mail.com").is_err());
+        suite.refund("alice", "dave@mail.com").unwrap();
+        assert_eq!(suite.balance_of("alice"), START - 10);
+        assert_eq!(suite.balance(&suite.contract), 10);
+    }
+
+    #[test]
+    fn transfer_must_be_paid_for() {
+        let mut suite = Suite::new(&["alice", "bob"]);
+        suite.register("bob", "bob").unwrap();
+
+        // nothing, too little, too much or the wrong denom attached
+        for funds in [
+            vec![],
+            vec![Coin::new(999u128, DENOM)],
+            vec![Coin::new(1_001u128, DENOM)],
+            vec![Coin::new(1_000u128, "uatom")],
+            vec![Coin::new(1_000u128, "uatom"), Coin::new(1_000u128, DENOM)],
+        ] {
+            let err = error(suite.execute("alice", transfer("bob", 1_000), &funds));
+            assert_eq!(err, "Generic error: Attach exactly 1000uxion to send this tip");
+            let err = error(suite.execute("alice", transfer("erin@mail.com", 1_000), &funds));
+            assert_eq!(err, "Generic error: Attach exactly 1000uxion to send this tip");
+        }
+        assert!(suite.escrows("erin@mail.com").escrows.is_empty());
+        assert_eq!(suite.fee_pool(), 0);
+
+        // tips from an internal balance take no funds at all
+        let from_balance = ExecuteMsg::Transfer {
+            identifier: "bob".to_string(),
+            amount: Coin::new(1_000u128, DENOM),
+            memo: None,
+            secret_hash: None,
+            from_balance: true,
+        };
+        let err = error(suite.execute("alice", from_balance, &[Coin::new(1_000u128, DENOM)]));
+        assert_eq!(err, "Generic error: Do not attach funds when tipping from balance");
+
+        // an unfunded tip would otherwise be paid out of funds held for others
+        suite.tip("alice", "erin@mail.com", 1_000).unwrap();
+        assert!(suite.execute("bob", transfer("bob", 1_000), &[]).is_err());
+        assert_eq!(suite.balance_of("bob"), START);
+        assert_eq!(suite.balance(&suite.contract), 1_000);
+        assert_eq!(suite.escrows("erin@mail.com").escrows.len(), 1);
+    }
+
+    #[test]
+    fn escrows_for_the_same_identifier_are_kept_apart() {
+        let mut suite = Suite::new(&["alice", "dave"]);
+
+        // a second tip must not replace the first one
+        suite.tip("alice", "erin@mail.com", 1_000).unwrap();
+        suite.tip("dave", "erin@mail.com", 500).unwrap();
+        let escrows = suite.escrows("erin@mail.com").escrows;
+        let held: Vec<(Addr, u128)> = escrows.iter().map(|e| (e.sender.clone(), e.amount.amount.u128())).collect();
+        assert_eq!(held, vec![(suite.addr("alice"), 990), (suite.addr("dave"), 495)]);
+        assert_eq!(suite.balance(&suite.contract), 1_500);
+
+        // each sender can only take back their own
+        suite.refund("dave", "erin@mail.com").unwrap();
+        assert_eq!(suite.balance_of("dave"), START - 5);
+        assert_eq!(suite.escrows("erin@mail.com").escrows.len(), 1);
+
+        suite.register("erin", "erin@mail.com").unwrap();
+        suite.claim("erin", "erin@mail.com").unwrap();
+        assert_eq!(suite.balance_of("erin"), 990);
+        assert_eq!(suite.balance_of("alice"), START - 1_000);
+
+        // only the two fees are left
+        suite.withdraw_fees().unwrap();
+        assert_eq!(suite.balance(&suite.platform_wallet), 15);
+        assert_eq!(suite.balance(&suite.contract), 0);
+    }
+
+    #[test]
+    fn claim_pending_on_register_pays_out_in_the_same_tx() {
+        let mut suite = Suite::new(&["alice", "dave"]);
+        suite.tip("alice", "frank@mail.com", 2_000).unwrap();
+        suite.tip("dave", "frank@mail.com", 1_000).unwrap();
+
+        suite.execute("frank", ExecuteMsg::Register {
+            identifier: "frank@mail.com".to_string(),
+            claim_pending: true,
+            referrer: None,
+        }, &[]).unwrap();
+        assert_eq!(suite.balance_of("frank"), 2_970);
+        assert_eq!(suite.balance(&suite.contract), 30);
+
+        // later tips go straight to frank
+        suite.tip("alice", "frank@mail.com", 100).unwrap();
+        assert_eq!(suite.balance_of("frank"), 3_069);
+    }
+
+    #[test]
+    fn failed_tip_returns_the_attached_funds() {
+        let mut suite = Suite::new(&["alice"]);
+        assert!(suite.execute("alice", transfer("", 1_000), &[Coin::new(1_000u128, DENOM)]).is_err());
+        assert_eq!(suite.balance_of("alice"), START);
+    }
+
+    #[test]
+    fn typed_client_drives_the_contract() {
+        let mut suite = Suite::new(&["alice"]);
+        let client = SocialTipContract(suite.contract.clone());
+        let alice = suite.addr("alice");
+        let bob = suite.addr("bob");
+
+        // funds are attached for a normal tip and left off when tipping from balance
+        match client.transfer("bob", Coin::new(1_000u128, DENOM), None, None).unwrap() {
+            CosmosMsg::Wasm(WasmMsg::Execute { funds, .. }) => assert_eq!(funds, vec![Coin::new(1_000u128, DENOM)]),
+            _ => panic!("expected a wasm execute"),
+        }
+        match client.transfer_from_balance("bob", Coin::new(1_000u128, DENOM), None, None).unwrap() {
+            CosmosMsg::Wasm(WasmMsg::Execute { funds, .. }) => assert!(funds.is_empty()),
+            _ => panic!("expected a wasm execute"),
+        }
+
+        suite.app.execute(alice.clone(), client.transfer("bob", Coin::new(1_000u128, DENOM), None, None).unwrap()).unwrap();
+        suite.app.execute(bob.clone(), client.register("bob", false, None).unwrap()).unwrap();
+        let escrows = client.escrows(&suite.app.wrap(), "bob", None, None).unwrap();
+        assert_eq!(escrows.escrows.len(), 1);
+        suite.app.execute(bob.clone(), client.claim("bob", None, None, None).unwrap()).unwrap();
+        assert_eq!(suite.balance_of("bob"), 990);
+
+        suite.app.execute(alice.clone(), client.deposit(vec![Coin::new(500u128, DENOM)]).unwrap()).unwrap();
+        suite.app.execute(alice.clone(), client.transfer_from_balance("bob", Coin::new(500u128, DENOM), None, None).unwrap()).unwrap();
+        let held = client.internal_balance(&suite.app.wrap(), bob.as_str()).unwrap();
+        assert_eq!(held.balances, vec![Coin::new(495u128, DENOM)]);
+
+        assert_eq!(client.account(&suite.app.wrap(), "bob").unwrap().address, Some(bob));
+        assert_eq!(client.config(&suite.app.wrap()).unwrap().token_denom, DENOM);
+        assert_eq!(client.fee_pool(&suite.app.wrap()).unwrap().accrued, vec![Coin::new(15u128, DENOM)]);
+        assert_eq!(client.tips_sent(&suite.app.wrap(), alice.as_str(), None, None).unwrap().tips.len(), 2);
+    }
+
+    #[test]
+    fn snapshot_checks_out_and_diffs_against_the_previous_one() {
+        let mut suite = Suite::new(&["alice", "bob"]);
+        // one entry per page, so every section with more than one entry is stitched
+        let query = |suite: &Suite| -> Snapshot {
+            fetch(&suite.app.wrap(), &SocialTipContract(suite.contract.clone()), Some(1)).unwrap()
+        };
+        suite.register("bob", "bob").unwrap();
+        suite.tip("alice", "bob", 1_000).unwrap();
+        suite.tip("alice", "carol", 2_000).unwrap();
+        suite.tip("bob", "carol", 500).unwrap();
+        let before = query(&suite);
+        assert_eq!(before.header.format, SNAPSHOT_FORMAT);
+        assert_eq!(before.sections.escrows.len(), 2);
+        assert_eq!(before.sections.tips.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(before.header.holdings, vec![Coin::new(1_980u128 + 495 + 35, DENOM)]);
+        assert_eq!(check(&before), Vec::<String>::new());
+        assert_eq!(fetch(&suite.app.wrap(), &SocialTipContract(suite.contract.clone()), None).unwrap(), before);
+
+        // pages from another height are not stitched in
+        let client = SocialTipContract(suite.contract.clone());
+        let mut page = client.snapshot_page(&suite.app.wrap(), SnapshotSection::Tips, None, Some(2)).unwrap();
+        assert_eq!(page.items.tips.len(), 2);
+        assert!(page.next.is_some());
+        page.height += 1;
+        let err = assemble(before.header.clone(), vec![page]).unwrap_err();
+        assert!(err.starts_with("Tips page is from height"), "{}", err);
+
+        // a snapshot survives the trip through a file
+        let reloaded: Snapshot = from_json(to_json_vec(&before).unwrap()).unwrap();
+        assert_eq!(reloaded, before);
+
+        suite.refund("alice", "carol").unwrap();
+        suite.withdraw_fees().unwrap();
+        let after = query(&suite);
+        assert_eq!(check(&after), Vec::<String>::new());
+        let changes = diff(&before, &after).unwrap();
+        assert!(changes.iter().any(|c| c.starts_with("~ holdings")));
+        assert!(changes.iter().any(|c| c.starts_with("- escrows[2]")));
+        assert!(changes.iter().any(|c| c.starts_with("~ tips[2]")));
+        assert!(changes.iter().any(|c| c.starts_with("- fee_pool[\"uxion\"]")));
+        assert!(!changes.iter().any(|c| c.contains("escrows[3]")));
+        assert_eq!(diff(&after, &after).unwrap(), Vec::<String>::new());
+
+        // tampering is caught
+        let mut broken = after.clone();
+        broken.header.holdings = vec![Coin::new(1u128, DENOM)];
+        broken.sections.tips.retain(|tip| tip.id != 3);
+        broken.sections.escrows[0].amount.amount += cosmwasm_std::Uint128::new(1);
+        let problems = check(&broken);
+        assert!(problems.iter().any(|p| p.starts_with("contract holds 1uxion but owes")), "{:?}", problems);
+        assert!(problems.iter().any(|p| p == "tip_records is 3 but 2 tips are stored"), "{:?}", problems);
+        assert!(problems.iter().any(|p| p == "escrow 3 has no tip record"), "{:?}", problems);
+    }
+
+    // Random sequences of user operations, checked after every step against a
+    // model of who is owed what.
+    mod properties {
+        use std::collections::BTreeMap;
+
+        use proptest::prelude::*;
+
+        use super::*;
+
+        const USERS: [&str; 4] = ["alice", "bob", "carol", "dave"];
+        const IDENTIFIERS: [&str; 3] = ["ann@mail.com", "ben@mail.com", "cat@mail.com"];
+
+        #[derive(Clone, Debug)]
+        enum Op {
+            Register { user: usize, identifier: usize },
+            Transfer { user: usize, identifier: usize, amount: u128 },
+            Claim { user: usize, identifier: usize },
+            Refund { user: usize, identifier: usize },
+            WithdrawFees,
+        }
+
+        fn op() -> impl Strategy<Value = Op> {
+            let pick = || (0..USERS.len(), 0..IDENTIFIERS.len());
+            prop_oneof![
+                1 => pick().prop_map(|(user, identifier)| Op::Register { user, identifier }),
+                4 => (pick(), 1..5_000u128).prop_map(|((user, identifier), amount)| Op::Transfer { user, identifier, amount }),
+                1 => pick().prop_map(|(user, identifier)| Op::Claim { user, identifier }),
+                1 => pick().prop_map(|(user, identifier)| Op::Refund { user, identifier }),
+                1 => Just(Op::WithdrawFees),
+            ]
+        }
+
+        proptest! {
+            #![proptest_config(ProptestConfig::with_cases(64))]
+
+            #[test]
+            fn random_operations_keep_the_books_balanced(ops in prop::collection::vec(op(), 1..40)) {
+                let mut suite = Suite::new(&USERS);
+                // identifier -> owning user, and (identifier, sender) -> net amount escrowed
+                let mut owners: BTreeMap<usize, usize> = BTreeMap::new();
+                let mut escrowed: BTreeMap<(usize, usize), u128> = BTreeMap::new();
+
+                for op in ops {
+                    let fees_before = suite.fee_pool();
+                    match op {
+                        Op::Register { user, identifier } => {
+                            if suite.register(USERS[user], IDENTIFIERS[identifier]).is_ok() {
+                                prop_assert!(owners.insert(identifier, user).is_none());
+                            }
+                        }
+                        Op::Transfer { user, identifier, amount } => {
+                            if suite.tip(USERS[user], IDENTIFIERS[identifier], amount).is_ok() {
+                                // 1% of every tip, give or take rounding
+                                let fee = suite.fee_pool() - fees_before;
+                                prop_assert!(fee.abs_diff(amount / 100) <= 1, "fee {} on {}", fee, amount);
+                                if !owners.contains_key(&identifier) {
+                                    *escrowed.entry((identifier, user)).or_default() += amount - fee;
+                                }
+                            }
+                        }
+                        Op::Claim { user, identifier } => {
+                            let before = suite.balance_of(USERS[user]);
+                            if suite.claim(USERS[user], IDENTIFIERS[identifier]).is_ok() {
+                                prop_assert_eq!(owners.get(&identifier), Some(&user));
+                                let owed: u128 = escrowed.iter().filter(|((i, _), _)| *i == identifier).map(|(_, a)| a).sum();
+                                escrowed.retain(|(i, _), _| *i != identifier);
+                                prop_assert_eq!(suite.balance_of(USERS[user]) - before, owed);
+                            }
+                        }
+                        Op::Refund { user, identifier } => {
+                            let before = suite.balance_of(USERS[user]);
+                            if suite.refund(USERS[user], IDENTIFIERS[identifier]).is_ok() {
+                                let owed = escrowed.remove(&(identifier, user)).unwrap_or_default();
+                                prop_assert!(owed > 0);
+                                prop_assert_eq!(suite.balance_of(USERS[user]) - before, owed);
+                            }
+                        }
+                        Op::WithdrawFees => {
+                            let before = suite.balance(&suite.platform_wallet);
+                            if suite.withdraw_fees().is_ok() {
+                                prop_assert_eq!(suite.balance(&suite.platform_wallet) - before, fees_before);
+                            }
+                        }
+                    }
+
+                    // nothing is created or destroyed
+                    let held: u128 = USERS.iter().map(|user| suite.balance_of(user)).sum();
+                    let total = held + suite.balance(&suite.contract) + suite.balance(&suite.platform_wallet);
+                    prop_assert_eq!(total, START * USERS.len() as u128);
+
+                    // every escrow the model expects is still there, and the
+                    // contract holds exactly the escrows plus the fee pool
+                    let mut on_chain: BTreeMap<(usize, usize), u128> = BTreeMap::new();
+                    for (i, identifier) in IDENTIFIERS.iter().enumerate() {
+                        for escrow in suite.all_escrows(identifier) {
+                            let sender = USERS.iter().position(|u| suite.addr(u) == escrow.sender).unwrap();
+                            *on_chain.entry((i, sender)).or_default() += escrow.amount.amount.u128();
+                        }
+                    }
+                    prop_assert_eq!(&on_chain, &escrowed);
+                    let escrow_total: u128 = on_chain.values().sum();
+                    prop_assert_eq!(suite.balance(&suite.contract), escrow_total + suite.fee_pool());
+                }
+            }
+        }
+    }
+}
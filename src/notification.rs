@@ -0,0 +1,227 @@
+use cosmwasm_std::{from_json, Addr, Coin, StdError, StdResult, Timestamp, Uint128};
+use serde::Deserialize;
+
+use crate::events::{EVENT_NAMESPACE, EVENT_SCHEMA_VERSION};
+use crate::helpers::{claim_commitment, claim_id};
+use crate::state::Escrow;
+
+// Off-chain side of the escrow event: turns a transaction's JSON (as returned
+// by the node's tx endpoints) into typed jobs for the claim-link mailer.
+
+// one "your tip is waiting" email to send
+#[derive(Clone, Debug, PartialEq)]
+pub struct NotificationJob {
+    pub tx_hash: Option<String>,
+    pub tip_id: u64,
+    pub claim_id: String,
+    pub commitment: String,
+    pub expires_at: Timestamp,
+    pub identifier: String,
+    pub sender: String,
+    pub denom: String,
+    pub net_amount: Uint128,
+}
+
+impl NotificationJob {
+    // True if the job matches `escrow`, as loaded from the chain with
+    // GetEscrowById just before sending. The commitment is an unkeyed hash
+    // anyone can recompute, so only the on-chain escrow shows the job is real
+    // and still claimable in full.
+    pub fn verify(&self, escrow: &Escrow) -> bool {
+        let amount = Coin::new(self.net_amount, &self.denom);
+        escrow.tip_id == self.tip_id
+            && escrow.identifier == self.identifier
+            && escrow.sender.as_str() == self.sender
+            && escrow.amount == amount
+            && escrow.expires_at == self.expires_at
+            && claim_commitment(&self.claim_id, &self.identifier, &amount, self.expires_at) == self.commitment
+    }
+
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        now >= self.expires_at
+    }
+}
+
+#[derive(Deserialize)]
+struct TxJson {
+    #[serde(default)]
+    txhash: Option<String>,
+    #[serde(default)]
+    events: Vec<TxEvent>,
+}
+
+#[derive(Deserialize)]
+struct TxEvent {
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(default)]
+    attributes: Vec<TxAttribute>,
+}
+
+#[derive(Deserialize)]
+struct TxAttribute {
+    key: String,
+    #[serde(default)]
+    value: String,
+}
+
+// Parse every escrow event `contract` emitted in a tx response. Any contract
+// can emit an event of the same type, so events from other contracts are
+// skipped, as are other actions and unknown schema versions.
+pub fn parse_notification_jobs(tx_json: &[u8], contract: &Addr) -> StdResult<Vec<NotificationJob>> {
+    let tx: TxJson = from_json(tx_json)?;
+    let escrow_type = format!("wasm-{}.escrow", EVENT_NAMESPACE);
+    tx.events
+        .iter()
+        .filter(|event| event.ty == escrow_type)
+        .filter(|event| attr(event, "_contract_address").ok() == Some(contract.as_str()))
+        .filter(|event| attr(event, "schema_version").ok() == Some(EVENT_SCHEMA_VERSION))
+        .map(|event| job_from_event(tx.txhash.clone(), contract, event))
+        .collect()
+}
+
+fn job_from_event(tx_hash: Option<String>, contract: &Addr, event: &TxEvent) -> StdResult<NotificationJob> {
+    let tip_id = parse_num(attr(event, "tip_id")?)?;
+    let id = attr(event, "claim_id")?;
+    if id != claim_id(contract, tip_id) {
+        return Err(StdError::generic_err(format!("claim_id of tip {} does not match the contract", tip_id)));
+    }
+    Ok(NotificationJob {
+        tx_hash,
+        tip_id,
+        claim_id: id.to_string(),
+        commitment: attr(event, "commitment")?.to_string(),
+        expires_at: Timestamp::from_seconds(parse_num(attr(event, "expires_at")?)?),
+        identifier: attr(event, "identifier")?.to_string(),
+        sender: attr(event, "sender")?.to_string(),
+        denom: attr(event, "denom")?.to_string(),
+        net_amount: Uint128::new(parse_num(attr(event, "net_amount")?)?),
+    })
+}
+
+fn attr<'a>(event: &'a TxEvent, key: &str) -> StdResult<&'a str> {
+    event
+        .attributes
+        .iter()
+        .find(|a| a.key == key)
+        .map(|a| a.value.as_str())
+        .ok_or_else(|| StdError::generic_err(format!("escrow event is missing `{}`", key)))
+}
+
+fn parse_num<T: std::str::FromStr>(value: &str) -> StdResult<T> {
+    value
+        .parse()
+        .map_err(|_| StdError::generic_err(format!("invalid number `{}`", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tx_json(contract: &str, claim_id: &str, commitment: &str) -> String {
+        format!(
+            r#"{{
+                "height": "42",
+                "txhash": "ABCDEF",
+                "events": [
+                    {{"type": "message", "attributes": [{{"key": "module", "value": "wasm"}}]}},
+                    {{"type": "wasm-social_tip.escrow", "attributes": [
+                        {{"key": "_contract_address", "value": "{}"}},
+                        {{"key": "schema_version", "value": "1"}},
+                        {{"key": "tip_id", "value": "3"}},
+                        {{"key": "sender", "value": "alice"}},
+                        {{"key": "identifier", "value": "bob@mail.com"}},
+                        {{"key": "denom", "value": "uxion"}},
+                        {{"key": "gross_amount", "value": "1000"}},
+                        {{"key": "fee", "value": "10"}},
+                        {{"key": "net_amount", "value": "990"}},
+                        {{"key": "claim_id", "value": "{}"}},
+                        {{"key": "commitment", "value": "{}"}},
+                        {{"key": "expires_at", "value": "1700000000"}}
+                    ]}},
+                    {{"type": "wasm-social_tip.escrow", "attributes": [
+                        {{"key": "schema_version", "value": "2"}}
+                    ]}}
+                ]
+            }}"#,
+            contract, claim_id, commitment
+        )
+    }
+
+    fn escrow() -> Escrow {
+        Escrow {
+            sender: Addr::unchecked("alice"),
+            identifier: "bob@mail.com".to_string(),
+            amount: Coin::new(990u128, "uxion"),
+            tip_id: 3,
+            expires_at: Timestamp::from_seconds(1_700_000_000),
+            secret_hash: None,
+        }
+    }
+
+    #[test]
+    fn parses_escrow_events() {
+        let contract = Addr::unchecked("contract");
+        let id = claim_id(&contract, 3);
+        let commitment = claim_commitment(
+            &id,
+            "bob@mail.com",
+            &Coin::new(990u128, "uxion"),
+            Timestamp::from_seconds(1_700_000_000),
+        );
+        let jobs = parse_notification_jobs(tx_json("contract", &id, &commitment).as_bytes(), &contract).unwrap();
+
+        // the unknown schema version is skipped
+        assert_eq!(jobs, vec![NotificationJob {
+            tx_hash: Some("ABCDEF".to_string()),
+            tip_id: 3,
+            claim_id: id,
+            commitment,
+            expires_at: Timestamp::from_seconds(1_700_000_000),
+            identifier: "bob@mail.com".to_string(),
+            sender: "alice".to_string(),
+            denom: "uxion".to_string(),
+            net_amount: Uint128::new(990),
+        }]);
+        assert!(jobs[0].verify(&escrow()));
+        assert!(!jobs[0].is_expired(Timestamp::from_seconds(1_699_999_999)));
+        assert!(jobs[0].is_expired(Timestamp::from_seconds(1_700_000_000)));
+    }
+
+    #[test]
+    fn rejects_tampered_commitment() {
+        let contract = Addr::unchecked("contract");
+        let tx = tx_json("contract", &claim_id(&contract, 3), "deadbeef");
+        let jobs = parse_notification_jobs(tx.as_bytes(), &contract).unwrap();
+        assert!(!jobs[0].verify(&escrow()));
+    }
+
+    #[test]
+    fn ignores_events_from_other_contracts() {
+        let contract = Addr::unchecked("contract");
+        let forger = Addr::unchecked("forger");
+        // a well-formed event, consistent with itself, from the wrong contract
+        let id = claim_id(&forger, 3);
+        let commitment = claim_commitment(&id, "bob@mail.com", &Coin::new(990u128, "uxion"), Timestamp::from_seconds(1_700_000_000));
+        let tx = tx_json("forger", &id, &commitment);
+        assert_eq!(parse_notification_jobs(tx.as_bytes(), &contract).unwrap(), vec![]);
+
+        // a claim id that is not this contract's for the tip is rejected
+        let tx = tx_json("contract", &id, &commitment);
+        let err = parse_notification_jobs(tx.as_bytes(), &contract).unwrap_err();
+        assert_eq!(err, StdError::generic_err("claim_id of tip 3 does not match the contract"));
+    }
+
+    #[test]
+    fn verifies_against_the_escrow_on_chain() {
+        let contract = Addr::unchecked("contract");
+        let id = claim_id(&contract, 3);
+        let commitment = claim_commitment(&id, "bob@mail.com", &Coin::new(990u128, "uxion"), Timestamp::from_seconds(1_700_000_000));
+        let jobs = parse_notification_jobs(tx_json("contract", &id, &commitment).as_bytes(), &contract).unwrap();
+        // partly claimed or redirected since the event was emitted
+        let claimed = Escrow { amount: Coin::new(500u128, "uxion"), ..escrow() };
+        assert!(!jobs[0].verify(&claimed));
+        let other = Escrow { identifier: "eve@mail.com".to_string(), ..escrow() };
+        assert!(!jobs[0].verify(&other));
+    }
+}
@@ -0,0 +1,55 @@
+use cosmwasm_std::{Addr, BankMsg, Coin, StdResult, Storage, Uint128};
+
+use crate::fees::TOTAL_BPS;
+use crate::state::{PayoutPolicy, BALANCES};
+
+// Bank messages that deliver a tip's net amount to a registered recipient
+// according to their payout policy. Held tips are credited to the recipient's
+// internal balance instead and produce no message.
+pub fn pay_out(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    policy: &PayoutPolicy,
+    net: &Coin,
+) -> StdResult<Vec<BankMsg>> {
+    let send = |to: &Addr, amount: Uint128| BankMsg::Send {
+        to_address: to.to_string(),
+        amount: vec![Coin::new(amount, &net.denom)],
+    };
+    let msgs = match policy {
+        PayoutPolicy::Direct => vec![send(owner, net.amount)],
+        PayoutPolicy::Forward { address } => vec![send(address, net.amount)],
+        PayoutPolicy::Split { savings, bps } => {
+            let saved = net.amount.multiply_ratio(*bps, TOTAL_BPS);
+            [(owner, net.amount - saved), (savings, saved)]
+                .into_iter()
+                .filter(|(_, amount)| !amount.is_zero())
+                .map(|(to, amount)| send(to, amount))
+                .collect()
+        }
+        PayoutPolicy::Hold => {
+            credit(storage, owner, net)?;
+            vec![]
+        }
+    };
+    Ok(msgs)
+}
+
+pub fn credit(storage: &mut dyn Storage, owner: &Addr, amount: &Coin) -> StdResult<()> {
+    let key = (owner.clone(), amount.denom.clone());
+    let balance = BALANCES.may_load(storage, key.clone())?.unwrap_or_default();
+    BALANCES.save(storage, key, &(balance + amount.amount))
+}
+
+// take from an internal balance, failing if it does not cover the amount
+pub fn debit(storage: &mut dyn Storage, owner: &Addr, amount: &Coin) -> StdResult<()> {
+    let key = (owner.clone(), amount.denom.clone());
+    let balance = BALANCES.may_load(storage, key.clone())?.unwrap_or_default();
+    let left = balance.checked_sub(amount.amount)?;
+    if left.is_zero() {
+        BALANCES.remove(storage, key);
+        Ok(())
+    } else {
+        BALANCES.save(storage, key, &left)
+    }
+}
@@ -0,0 +1,30 @@
+use cosmwasm_std::{Storage, Timestamp};
+use cw_storage_plus::{Map, PrimaryKey};
+
+use crate::error::ContractError;
+
+// Sliding window counter: keeps the timestamps of recent events under `key`,
+// forgets the ones older than `window` seconds and rejects a new event once
+// `max` events remain inside the window. The log never grows beyond `max`.
+pub fn hit<'a, K>(
+    storage: &mut dyn Storage,
+    log: &Map<K, Vec<Timestamp>>,
+    key: K,
+    now: Timestamp,
+    window: u64,
+    max: u32,
+    err: ContractError,
+) -> Result<(), ContractError>
+where
+    K: PrimaryKey<'a> + Clone,
+{
+    let mut times = log.may_load(storage, key.clone())?.unwrap_or_default();
+    times.retain(|t| t.plus_seconds(window) > now);
+    if times.len() >= max as usize {
+        return Err(err);
+    }
+    times.push(now);
+    log.save(storage, key, &times)?;
+    Ok(())
+}
+
@@ -0,0 +1,40 @@
+use cosmwasm_std::{to_json_vec, Addr, Api, CanonicalAddr, StdError, StdResult};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+use crate::msg::{ClaimPayload, SignatureScheme};
+
+// bytes the recipient signs off-chain: the payload's JSON, exactly as the
+// contract serializes it
+pub fn sign_bytes(payload: &ClaimPayload) -> StdResult<Vec<u8>> {
+    to_json_vec(payload)
+}
+
+// The account a public key controls, using the usual Cosmos derivations:
+// ripemd160(sha256(pubkey)) for secp256k1 and sha256(pubkey)[..20] for ed25519.
+pub fn pubkey_to_addr(api: &dyn Api, scheme: &SignatureScheme, pubkey: &[u8]) -> StdResult<Addr> {
+    let canonical = match scheme {
+        SignatureScheme::Secp256k1 => Ripemd160::digest(Sha256::digest(pubkey)).to_vec(),
+        SignatureScheme::Ed25519 => Sha256::digest(pubkey)[..20].to_vec(),
+    };
+    api.addr_humanize(&CanonicalAddr::from(canonical))
+}
+
+// check the signature over the payload and return the signer's address
+pub fn verify_claim(
+    api: &dyn Api,
+    payload: &ClaimPayload,
+    scheme: &SignatureScheme,
+    pubkey: &[u8],
+    signature: &[u8],
+) -> StdResult<Addr> {
+    let message = sign_bytes(payload)?;
+    let valid = match scheme {
+        SignatureScheme::Secp256k1 => api.secp256k1_verify(&Sha256::digest(&message), signature, pubkey)?,
+        SignatureScheme::Ed25519 => api.ed25519_verify(&message, signature, pubkey)?,
+    };
+    if !valid {
+        return Err(StdError::generic_err("Invalid claim signature"));
+    }
+    pubkey_to_addr(api, scheme, pubkey)
+}
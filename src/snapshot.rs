@@ -0,0 +1,532 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{
+    Addr, Binary, Coin, Deps, Env, Order, QuerierWrapper, Record, StdResult, Storage, Timestamp, Uint128,
+};
+use cw2::get_contract_version;
+use cw_storage_plus::{Bound, KeyDeserialize, Map, PrimaryKey};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::fees::TOTAL_BPS;
+use crate::helpers::SocialTipContract;
+use crate::state::{
+    escrows, received_totals, sent_totals, tips, Allowance, BlockEntry, Config, DenomStats, Escrow, MicroTipBucket,
+    PauseState, PayoutPolicy, Profile, Referral, ReferralEarnings, Tip, TipStatus, TipTotal, ACCOUNTS, ALLOWANCES,
+    BALANCES, BLOCKED_ADDRESSES, BLOCKED_IDENTIFIERS, CLAIM_COMMITS, CONFIG, DENOM_STATS, FEE_POOL, INBOUND_ESCROW_LOG,
+    MICRO_TIP_BUCKETS, PAUSE, PAYOUT_POLICIES, PROFILES, REFERRALS, REFERRAL_EARNINGS, RELAY_NONCES,
+    SENDER_ESCROW_LOG, TIP_COUNT, TIP_RECORDS,
+};
+
+// bumped whenever a field is added, removed or changes meaning
+pub const SNAPSHOT_FORMAT: u32 = 1;
+
+// entries per snapshot page; the entries are small, so pages are larger than
+// for the other list queries
+const DEFAULT_PAGE: u32 = 50;
+const MAX_PAGE: u32 = 200;
+
+// one stored map entry
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Entry<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+// The single items of the state, plus what the bank holds for the contract.
+// Returned by the `snapshot {}` query; the maps are read page by page with
+// `snapshot_page`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SnapshotHeader {
+    pub format: u32,
+    pub contract: String,
+    pub version: String,
+    pub height: u64,
+    pub time: Timestamp,
+    // bank balance of the contract in every denom it has taken tips or fees in
+    pub holdings: Vec<Coin>,
+    pub config: Config,
+    pub pause: PauseState,
+    pub tip_count: u64,
+    pub tip_records: u64,
+}
+
+// Every stored map, each in key order.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Sections {
+    pub accounts: Vec<Entry<String, Addr>>,
+    pub escrows: Vec<Escrow>,
+    pub tips: Vec<Tip>,
+    pub balances: Vec<Entry<(Addr, String), Uint128>>,
+    pub fee_pool: Vec<Entry<String, Uint128>>,
+    pub micro_tip_buckets: Vec<Entry<(String, String), MicroTipBucket>>,
+    pub referrals: Vec<Entry<String, Referral>>,
+    pub referral_earnings: Vec<Entry<(String, String), ReferralEarnings>>,
+    pub payout_policies: Vec<Entry<String, PayoutPolicy>>,
+    pub profiles: Vec<Entry<String, Profile>>,
+    pub allowances: Vec<Entry<(Addr, Addr, String), Allowance>>,
+    pub blocked_addresses: Vec<Entry<Addr, BlockEntry>>,
+    pub blocked_identifiers: Vec<Entry<String, BlockEntry>>,
+    pub relay_nonces: Vec<Entry<Addr, u64>>,
+    pub claim_commits: Vec<Entry<(u64, String), u64>>,
+    pub sender_escrow_log: Vec<Entry<Addr, Vec<Timestamp>>>,
+    pub inbound_escrow_log: Vec<Entry<String, Vec<Timestamp>>>,
+    pub denom_stats: Vec<Entry<String, DenomStats>>,
+    pub sent_totals: Vec<Entry<(String, String), TipTotal>>,
+    pub received_totals: Vec<Entry<(String, String), TipTotal>>,
+}
+
+impl Sections {
+    // move the entries of another page in after ours
+    fn append(&mut self, other: &mut Sections) {
+        self.accounts.append(&mut other.accounts);
+        self.escrows.append(&mut other.escrows);
+        self.tips.append(&mut other.tips);
+        self.balances.append(&mut other.balances);
+        self.fee_pool.append(&mut other.fee_pool);
+        self.micro_tip_buckets.append(&mut other.micro_tip_buckets);
+        self.referrals.append(&mut other.referrals);
+        self.referral_earnings.append(&mut other.referral_earnings);
+        self.payout_policies.append(&mut other.payout_policies);
+        self.profiles.append(&mut other.profiles);
+        self.allowances.append(&mut other.allowances);
+        self.blocked_addresses.append(&mut other.blocked_addresses);
+        self.blocked_identifiers.append(&mut other.blocked_identifiers);
+        self.relay_nonces.append(&mut other.relay_nonces);
+        self.claim_commits.append(&mut other.claim_commits);
+        self.sender_escrow_log.append(&mut other.sender_escrow_log);
+        self.inbound_escrow_log.append(&mut other.inbound_escrow_log);
+        self.denom_stats.append(&mut other.denom_stats);
+        self.sent_totals.append(&mut other.sent_totals);
+        self.received_totals.append(&mut other.received_totals);
+    }
+}
+
+// one field of Sections
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotSection {
+    Accounts,
+    Escrows,
+    Tips,
+    Balances,
+    FeePool,
+    MicroTipBuckets,
+    Referrals,
+    ReferralEarnings,
+    PayoutPolicies,
+    Profiles,
+    Allowances,
+    BlockedAddresses,
+    BlockedIdentifiers,
+    RelayNonces,
+    ClaimCommits,
+    SenderEscrowLog,
+    InboundEscrowLog,
+    DenomStats,
+    SentTotals,
+    ReceivedTotals,
+}
+
+impl SnapshotSection {
+    pub const ALL: [SnapshotSection; 20] = [
+        SnapshotSection::Accounts,
+        SnapshotSection::Escrows,
+        SnapshotSection::Tips,
+        SnapshotSection::Balances,
+        SnapshotSection::FeePool,
+        SnapshotSection::MicroTipBuckets,
+        SnapshotSection::Referrals,
+        SnapshotSection::ReferralEarnings,
+        SnapshotSection::PayoutPolicies,
+        SnapshotSection::Profiles,
+        SnapshotSection::Allowances,
+        SnapshotSection::BlockedAddresses,
+        SnapshotSection::BlockedIdentifiers,
+        SnapshotSection::RelayNonces,
+        SnapshotSection::ClaimCommits,
+        SnapshotSection::SenderEscrowLog,
+        SnapshotSection::InboundEscrowLog,
+        SnapshotSection::DenomStats,
+        SnapshotSection::SentTotals,
+        SnapshotSection::ReceivedTotals,
+    ];
+}
+
+// Up to `limit` entries of one section, in `items` under that section's
+// field. `next` is the cursor for the following page, None on the last one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SnapshotPage {
+    pub height: u64,
+    pub section: SnapshotSection,
+    pub items: Sections,
+    pub next: Option<Binary>,
+}
+
+// A whole snapshot, stitched together from the header and every page.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Snapshot {
+    pub header: SnapshotHeader,
+    pub sections: Sections,
+}
+
+pub fn header(deps: Deps, env: &Env) -> StdResult<SnapshotHeader> {
+    let storage = deps.storage;
+    let version = get_contract_version(storage)?;
+    let config = CONFIG.load(storage)?;
+    // every denom the contract can owe has been tipped (and so has stats) or
+    // collected as a fee
+    let mut denoms = vec![config.token_denom.clone()];
+    for denom in DENOM_STATS.keys(storage, None, None, Order::Ascending).chain(FEE_POOL.keys(storage, None, None, Order::Ascending)) {
+        let denom = denom?;
+        if !denoms.contains(&denom) {
+            denoms.push(denom);
+        }
+    }
+    let holdings = denoms
+        .into_iter()
+        .map(|denom| deps.querier.query_balance(&env.contract.address, denom))
+        .collect::<StdResult<_>>()?;
+    Ok(SnapshotHeader {
+        format: SNAPSHOT_FORMAT,
+        contract: version.contract,
+        version: version.version,
+        height: env.block.height,
+        time: env.block.time,
+        holdings,
+        config,
+        pause: PAUSE.may_load(storage)?.unwrap_or_default(),
+        tip_count: TIP_COUNT.may_load(storage)?.unwrap_or_default(),
+        tip_records: TIP_RECORDS.may_load(storage)?.unwrap_or_default(),
+    })
+}
+
+// read up to `limit` records into `out` and return the cursor past the last
+// one, or None if there are no more
+fn collect<K: KeyDeserialize, V>(
+    records: Box<dyn Iterator<Item = StdResult<Record<V>>> + '_>,
+    limit: usize,
+    out: &mut Vec<Entry<K::Output, V>>,
+) -> StdResult<Option<Binary>> {
+    let mut last = None;
+    let mut read = 0;
+    for record in records.take(limit) {
+        let (key, value) = record?;
+        out.push(Entry { key: K::from_vec(key.clone())?, value });
+        last = Some(key);
+        read += 1;
+    }
+    Ok(if read < limit { None } else { last.map(Binary::from) })
+}
+
+fn after<'a, K: PrimaryKey<'a>>(start_after: &Option<Binary>) -> Option<Bound<'a, K>> {
+    start_after.as_ref().map(|key| Bound::ExclusiveRaw(key.to_vec()))
+}
+
+fn dump<'a, K, V>(
+    storage: &dyn Storage,
+    map: &Map<K, V>,
+    start_after: &Option<Binary>,
+    limit: usize,
+    out: &mut Vec<Entry<K::Output, V>>,
+) -> StdResult<Option<Binary>>
+where
+    K: PrimaryKey<'a> + KeyDeserialize,
+    V: Serialize + DeserializeOwned,
+{
+    collect::<K, V>(map.range_raw(storage, after(start_after), None, Order::Ascending), limit, out)
+}
+
+// One page of a section, starting after the raw key `start_after`.
+pub fn page(
+    deps: Deps,
+    env: &Env,
+    section: SnapshotSection,
+    start_after: Option<Binary>,
+    limit: Option<u32>,
+) -> StdResult<SnapshotPage> {
+    let storage = deps.storage;
+    let limit = limit.unwrap_or(DEFAULT_PAGE).clamp(1, MAX_PAGE) as usize;
+    let start = &start_after;
+    let mut items = Sections::default();
+    let next = match section {
+        SnapshotSection::Accounts => dump(storage, &ACCOUNTS, start, limit, &mut items.accounts)?,
+        SnapshotSection::Escrows => {
+            let mut entries = vec![];
+            let records = escrows().range_raw(storage, after(start), None, Order::Ascending);
+            let next = collect::<u64, _>(records, limit, &mut entries)?;
+            items.escrows = entries.into_iter().map(|e| e.value).collect();
+            next
+        }
+        SnapshotSection::Tips => {
+            let mut entries = vec![];
+            let records = tips().range_raw(storage, after(start), None, Order::Ascending);
+            let next = collect::<u64, _>(records, limit, &mut entries)?;
+            items.tips = entries.into_iter().map(|e| e.value).collect();
+            next
+        }
+        SnapshotSection::Balances => dump(storage, &BALANCES, start, limit, &mut items.balances)?,
+        SnapshotSection::FeePool => dump(storage, &FEE_POOL, start, limit, &mut items.fee_pool)?,
+        SnapshotSection::MicroTipBuckets => {
+            dump(storage, &MICRO_TIP_BUCKETS, start, limit, &mut items.micro_tip_buckets)?
+        }
+        SnapshotSection::Referrals => dump(storage, &REFERRALS, start, limit, &mut items.referrals)?,
+        SnapshotSection::ReferralEarnings => {
+            dump(storage, &REFERRAL_EARNINGS, start, limit, &mut items.referral_earnings)?
+        }
+        SnapshotSection::PayoutPolicies => dump(storage, &PAYOUT_POLICIES, start, limit, &mut items.payout_policies)?,
+        SnapshotSection::Profiles => dump(storage, &PROFILES, start, limit, &mut items.profiles)?,
+        SnapshotSection::Allowances => dump(storage, &ALLOWANCES, start, limit, &mut items.allowances)?,
+        SnapshotSection::BlockedAddresses => {
+            dump(storage, &BLOCKED_ADDRESSES, start, limit, &mut items.blocked_addresses)?
+        }
+        SnapshotSection::BlockedIdentifiers => {
+            dump(storage, &BLOCKED_IDENTIFIERS, start, limit, &mut items.blocked_identifiers)?
+        }
+        SnapshotSection::RelayNonces => dump(storage, &RELAY_NONCES, start, limit, &mut items.relay_nonces)?,
+        SnapshotSection::ClaimCommits => dump(storage, &CLAIM_COMMITS, start, limit, &mut items.claim_commits)?,
+        SnapshotSection::SenderEscrowLog => {
+            dump(storage, &SENDER_ESCROW_LOG, start, limit, &mut items.sender_escrow_log)?
+        }
+        SnapshotSection::InboundEscrowLog => {
+            dump(storage, &INBOUND_ESCROW_LOG, start, limit, &mut items.inbound_escrow_log)?
+        }
+        SnapshotSection::DenomStats => dump(storage, &DENOM_STATS, start, limit, &mut items.denom_stats)?,
+        SnapshotSection::SentTotals => {
+            let records = sent_totals().range_raw(storage, after(start), None, Order::Ascending);
+            collect::<(String, String), _>(records, limit, &mut items.sent_totals)?
+        }
+        SnapshotSection::ReceivedTotals => {
+            let records = received_totals().range_raw(storage, after(start), None, Order::Ascending);
+            collect::<(String, String), _>(records, limit, &mut items.received_totals)?
+        }
+    };
+    Ok(SnapshotPage { height: env.block.height, section, items, next })
+}
+
+// Put a header and its pages back together. Every page must come from the
+// header's height, or the result would mix two states.
+pub fn assemble(header: SnapshotHeader, pages: Vec<SnapshotPage>) -> Result<Snapshot, String> {
+    if header.format != SNAPSHOT_FORMAT {
+        return Err(format!("unsupported snapshot format {}", header.format));
+    }
+    let mut sections = Sections::default();
+    for mut page in pages {
+        if page.height != header.height {
+            return Err(format!("{:?} page is from height {}, the header from {}", page.section, page.height, header.height));
+        }
+        sections.append(&mut page.items);
+    }
+    Ok(Snapshot { header, sections })
+}
+
+// Read a whole snapshot through the querier, `limit` entries per page. The
+// queries run at the querier's current height; against a live node, pin the
+// height so every page sees the same state.
+pub fn fetch(querier: &QuerierWrapper, contract: &SocialTipContract, limit: Option<u32>) -> StdResult<Snapshot> {
+    let header = contract.snapshot(querier)?;
+    let mut pages = vec![];
+    for section in SnapshotSection::ALL {
+        let mut start_after = None;
+        loop {
+            let page = contract.snapshot_page(querier, section, start_after, limit)?;
+            start_after = page.next.clone();
+            pages.push(page);
+            if start_after.is_none() {
+                break;
+            }
+        }
+    }
+    assemble(header, pages).map_err(cosmwasm_std::StdError::generic_err)
+}
+
+// What the contract owes per denom: internal balances, escrows, pending micro
+// tips, unpaid referral rewards and fees not yet withdrawn.
+pub fn liabilities(sections: &Sections) -> BTreeMap<String, Uint128> {
+    let mut owed: BTreeMap<String, Uint128> = BTreeMap::new();
+    let mut add = |denom: &str, amount: Uint128| *owed.entry(denom.to_string()).or_default() += amount;
+    for entry in &sections.balances {
+        add(&entry.key.1, entry.value);
+    }
+    for escrow in &sections.escrows {
+        add(&escrow.amount.denom, escrow.amount.amount);
+    }
+    for entry in &sections.micro_tip_buckets {
+        add(&entry.key.1, entry.value.gross);
+    }
+    for entry in &sections.referral_earnings {
+        add(&entry.key.1, entry.value.unpaid);
+    }
+    for entry in &sections.fee_pool {
+        add(&entry.key, entry.value);
+    }
+    owed
+}
+
+// Invariants every snapshot of a healthy contract satisfies. Returns one line
+// per problem found, none if the snapshot checks out.
+pub fn check(snapshot: &Snapshot) -> Vec<String> {
+    let Snapshot { header, sections } = snapshot;
+    let mut problems = vec![];
+    if header.format != SNAPSHOT_FORMAT {
+        problems.push(format!("unsupported snapshot format {}", header.format));
+        return problems;
+    }
+
+    let total_bps: u32 = header.config.fee_shares.iter().map(|s| u32::from(s.bps)).sum();
+    if total_bps != u32::from(TOTAL_BPS) {
+        problems.push(format!("fee shares add up to {} bps", total_bps));
+    }
+
+    // the bank must cover everything owed
+    for (denom, owed) in liabilities(sections) {
+        let held = header
+            .holdings
+            .iter()
+            .find(|c| c.denom == denom)
+            .map(|c| c.amount)
+            .unwrap_or_default();
+        if held < owed {
+            problems.push(format!("contract holds {}{} but owes {}{}", held, denom, owed, denom));
+        }
+    }
+
+    // tip ledger
+    if header.tip_records != sections.tips.len() as u64 {
+        problems.push(format!("tip_records is {} but {} tips are stored", header.tip_records, sections.tips.len()));
+    }
+    let tips: BTreeMap<u64, &Tip> = sections.tips.iter().map(|tip| (tip.id, tip)).collect();
+    for tip in &sections.tips {
+        if tip.id == 0 || tip.id > header.tip_count {
+            problems.push(format!("tip {} is outside 1..={}", tip.id, header.tip_count));
+        }
+        if tip.fee + tip.net != tip.gross {
+            problems.push(format!("tip {}: fee {} + net {} != gross {}", tip.id, tip.fee, tip.net, tip.gross));
+        }
+    }
+
+    // every escrow belongs to an escrowed tip and holds no more than its net
+    for escrow in &sections.escrows {
+        match tips.get(&escrow.tip_id) {
+            None => problems.push(format!("escrow {} has no tip record", escrow.tip_id)),
+            Some(tip) if tip.status != TipStatus::Escrowed => {
+                problems.push(format!("escrow {} belongs to a tip marked {:?}", escrow.tip_id, tip.status))
+            }
+            Some(tip) if escrow.amount.denom != tip.denom || escrow.amount.amount > tip.net => {
+                problems.push(format!("escrow {} holds {} of a {}{} tip", escrow.tip_id, escrow.amount, tip.net, tip.denom))
+            }
+            Some(_) => {}
+        }
+        if escrow.amount.amount.is_zero() {
+            problems.push(format!("escrow {} is empty", escrow.tip_id));
+        }
+    }
+
+    // every tip a bucket lists is still waiting to be settled
+    for entry in &sections.micro_tip_buckets {
+        for id in &entry.value.tip_ids {
+            if let Some(tip) = tips.get(id).filter(|tip| tip.status != TipStatus::Batched) {
+                problems.push(format!("micro-tip bucket for {} lists tip {} marked {:?}", entry.key.0, id, tip.status));
+            }
+        }
+    }
+
+    // nothing is kept for zero
+    for entry in &sections.balances {
+        if entry.value.is_zero() {
+            problems.push(format!("zero balance kept for {} in {}", entry.key.0, entry.key.1));
+        }
+    }
+    for entry in &sections.micro_tip_buckets {
+        if entry.value.gross.is_zero() || entry.value.tips == 0 {
+            problems.push(format!("empty micro-tip bucket for {} in {}", entry.key.0, entry.key.1));
+        }
+    }
+    for entry in &sections.referral_earnings {
+        if entry.value.unpaid > entry.value.earned {
+            problems.push(format!("{} has more unpaid than earned in {}", entry.key.0, entry.key.1));
+        }
+    }
+
+    // per-identifier settings only exist for registered identifiers
+    let registered = |identifier: &String| sections.accounts.iter().any(|a| &a.key == identifier);
+    let owned = [
+        ("payout policy", sections.payout_policies.iter().map(|e| &e.key).collect::<Vec<_>>()),
+        ("profile", sections.profiles.iter().map(|e| &e.key).collect()),
+        ("referral", sections.referrals.iter().map(|e| &e.key).collect()),
+    ];
+    for (what, identifiers) in owned {
+        for identifier in identifiers.into_iter().filter(|i| !registered(i)) {
+            problems.push(format!("{} for unregistered identifier {}", what, identifier));
+        }
+    }
+    problems
+}
+
+fn json<T: Serialize>(value: &T) -> StdResult<String> {
+    cosmwasm_std::to_json_string(value)
+}
+
+fn single<T: Serialize>(value: &T) -> StdResult<BTreeMap<String, String>> {
+    Ok(BTreeMap::from([(String::new(), json(value)?)]))
+}
+
+fn entries<K: Serialize, V: Serialize>(entries: &[Entry<K, V>]) -> StdResult<BTreeMap<String, String>> {
+    entries.iter().map(|e| Ok((json(&e.key)?, json(&e.value)?))).collect()
+}
+
+fn by_id<T: Serialize>(items: &[T], id: impl Fn(&T) -> u64) -> StdResult<BTreeMap<String, String>> {
+    items.iter().map(|item| Ok((id(item).to_string(), json(item)?))).collect()
+}
+
+// Everything that changed between two snapshots, one line per added (+),
+// removed (-) or changed (~) entry. Height and time are left out.
+pub fn diff(old: &Snapshot, new: &Snapshot) -> StdResult<Vec<String>> {
+    let (a, b) = (&old.header, &new.header);
+    let (x, y) = (&old.sections, &new.sections);
+    let sections = [
+        ("format", single(&a.format)?, single(&b.format)?),
+        ("version", single(&a.version)?, single(&b.version)?),
+        ("holdings", single(&a.holdings)?, single(&b.holdings)?),
+        ("config", single(&a.config)?, single(&b.config)?),
+        ("pause", single(&a.pause)?, single(&b.pause)?),
+        ("tip_count", single(&a.tip_count)?, single(&b.tip_count)?),
+        ("tip_records", single(&a.tip_records)?, single(&b.tip_records)?),
+        ("accounts", entries(&x.accounts)?, entries(&y.accounts)?),
+        ("escrows", by_id(&x.escrows, |e| e.tip_id)?, by_id(&y.escrows, |e| e.tip_id)?),
+        ("tips", by_id(&x.tips, |t| t.id)?, by_id(&y.tips, |t| t.id)?),
+        ("balances", entries(&x.balances)?, entries(&y.balances)?),
+        ("fee_pool", entries(&x.fee_pool)?, entries(&y.fee_pool)?),
+        ("micro_tip_buckets", entries(&x.micro_tip_buckets)?, entries(&y.micro_tip_buckets)?),
+        ("referrals", entries(&x.referrals)?, entries(&y.referrals)?),
+        ("referral_earnings", entries(&x.referral_earnings)?, entries(&y.referral_earnings)?),
+        ("payout_policies", entries(&x.payout_policies)?, entries(&y.payout_policies)?),
+        ("profiles", entries(&x.profiles)?, entries(&y.profiles)?),
+        ("allowances", entries(&x.allowances)?, entries(&y.allowances)?),
+        ("blocked_addresses", entries(&x.blocked_addresses)?, entries(&y.blocked_addresses)?),
+        ("blocked_identifiers", entries(&x.blocked_identifiers)?, entries(&y.blocked_identifiers)?),
+        ("relay_nonces", entries(&x.relay_nonces)?, entries(&y.relay_nonces)?),
+        ("claim_commits", entries(&x.claim_commits)?, entries(&y.claim_commits)?),
+        ("sender_escrow_log", entries(&x.sender_escrow_log)?, entries(&y.sender_escrow_log)?),
+        ("inbound_escrow_log", entries(&x.inbound_escrow_log)?, entries(&y.inbound_escrow_log)?),
+        ("denom_stats", entries(&x.denom_stats)?, entries(&y.denom_stats)?),
+        ("sent_totals", entries(&x.sent_totals)?, entries(&y.sent_totals)?),
+        ("received_totals", entries(&x.received_totals)?, entries(&y.received_totals)?),
+    ];
+    let mut changes = vec![];
+    for (section, before, after) in sections {
+        let name = |key: &str| if key.is_empty() { section.to_string() } else { format!("{}[{}]", section, key) };
+        for (key, value) in &after {
+            match before.get(key) {
+                None => changes.push(format!("+ {} = {}", name(key), value)),
+                Some(old) if old != value => changes.push(format!("~ {}: {} -> {}", name(key), old, value)),
+                Some(_) => {}
+            }
+        }
+        for key in before.keys().filter(|key| !after.contains_key(*key)) {
+            changes.push(format!("- {}", name(key)));
+        }
+    }
+    Ok(changes)
+}
@@ -1,8 +1,8 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Coin};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Coin, Timestamp, Uint128};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 // store the token denomination
 pub const TOKEN_DENOM: Item<String> = Item::new("token_denom");
@@ -14,14 +14,402 @@ pub  const ACCOUNTS: Map<String, Addr> = Map::new("accounts");
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct Escrow {
     pub sender: Addr,
+    pub identifier: String,
     pub amount: Coin,
+    // id of the tip record this escrow belongs to, also the escrow id
+    pub tip_id: u64,
+    // when the emailed claim link stops being valid
+    pub expires_at: Timestamp,
+    // hex sha256 of the claim link secret, if the sender attached one
+    pub secret_hash: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct Config {
+    pub admin: Addr,
+    // may pause (but not unpause) register and transfer
+    pub guardian: Option<Addr>,
+    pub token_denom: String,
+    pub platform_wallet: Addr,
+    // how accrued fees are split on withdrawal, 100% to platform_wallet by default
+    pub fee_shares: Vec<FeeShare>,
+    pub tip_retention: RetentionPolicy,
+    pub rate_limits: RateLimits,
+    // lifetime of claim links sent for escrowed tips, in seconds
+    pub claim_link_ttl: u64,
+    // most a relayer may be paid out of a relayed claim, in token_denom
+    pub max_relayer_fee: Uint128,
+    // tips to registered identifiers below this go to a pending bucket that
+    // Settle pays out; zero turns batching off
+    #[serde(default)]
+    pub micro_tip_threshold: Uint128,
+    pub referral: ReferralPolicy,
+}
+
+// share of the platform fee paid to whoever referred a tip's recipient
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, JsonSchema)]
+pub struct ReferralPolicy {
+    // referrer's cut of the fee in basis points, 0 switches rewards off
+    pub bps: u16,
+    // seconds after the referred registration during which rewards are paid
+    pub duration: u64,
+}
+
+// anti-spam limits enforced on transfers
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, JsonSchema)]
+pub struct RateLimits {
+    // smallest accepted tip for each listed denom
+    pub min_tips: Vec<Coin>,
+    // length of the sliding window in seconds
+    pub window: u64,
+    // escrowed tips a sender may create per window
+    pub max_escrows_per_sender: Option<u32>,
+    // escrowed tips an identifier may receive per window
+    pub max_escrows_per_identifier: Option<u32>,
+}
+
+// how long settled tip records are kept before they may be pruned
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, JsonSchema)]
+pub struct RetentionPolicy {
+    // keep at most this many tip records
+    pub max_records: Option<u64>,
+    // prune settled records older than this many seconds
+    pub max_age: Option<u64>,
+}
+
+// operations that can be switched off independently
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Register,
+    Transfer,
+    Claim,
+    Refund,
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Operation::Register => "register",
+            Operation::Transfer => "transfer",
+            Operation::Claim => "claim",
+            Operation::Refund => "refund",
+        };
+        f.write_str(name)
+    }
+}
+
+// circuit breaker switches, everything open by default
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, JsonSchema)]
+pub struct PauseState {
+    pub register: bool,
+    pub transfer: bool,
+    pub claim: bool,
+    pub refund: bool,
+}
+
+impl PauseState {
+    pub fn is_paused(&self, op: Operation) -> bool {
+        match op {
+            Operation::Register => self.register,
+            Operation::Transfer => self.transfer,
+            Operation::Claim => self.claim,
+            Operation::Refund => self.refund,
+        }
+    }
+
+    pub fn set(&mut self, op: Operation, paused: bool) {
+        match op {
+            Operation::Register => self.register = paused,
+            Operation::Transfer => self.transfer = paused,
+            Operation::Claim => self.claim = paused,
+            Operation::Refund => self.refund = paused,
+        }
+    }
+}
+
+pub const PAUSE: Item<PauseState> = Item::new("pause");
+
+// why and when an address or identifier was put on the denylist
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct BlockEntry {
+    pub reason: Option<String>,
+    pub since: Timestamp,
+}
+
+pub const BLOCKED_ADDRESSES: Map<Addr, BlockEntry> = Map::new("blocked_addresses");
+pub const BLOCKED_IDENTIFIERS: Map<String, BlockEntry> = Map::new("blocked_identifiers");
+
+pub struct EscrowIndexes<'a> {
+    pub identifier: MultiIndex<'a, String, Escrow, u64>,
+}
+
+impl IndexList<Escrow> for EscrowIndexes<'_> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Escrow>> + '_> {
+        let v: Vec<&dyn Index<Escrow>> = vec![&self.identifier];
+        Box::new(v.into_iter())
+    }
+}
+
+// pending escrows keyed by tip id, so several tips to the same identifier
+// are held side by side instead of overwriting each other
+pub fn escrows<'a>() -> IndexedMap<u64, Escrow, EscrowIndexes<'a>> {
+    let indexes = EscrowIndexes {
+        identifier: MultiIndex::new(|_pk, e: &Escrow| e.identifier.clone(), "tip_escrows", "tip_escrows__identifier"),
+    };
+    IndexedMap::new("tip_escrows", indexes)
+}
+
+// config as the first release stored it, under the same key as CONFIG; only
+// read when migrating that release
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LegacyConfig {
     pub token_denom: String,
     pub platform_wallet: String,
 }
 
-pub const ESCROWS: Map<String, Escrow> = Map::new("escrows");
-pub const CONFIG: Item<Config> = Item::new("config");
\ No newline at end of file
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+
+// the first release's single escrow per identifier, moved into escrows() by
+// migrate
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LegacyEscrow {
+    pub sender: Addr,
+    pub amount: Coin,
+}
+
+pub const LEGACY_ESCROWS: Map<String, LegacyEscrow> = Map::new("escrows");
+
+// recent escrow times per sender and per identifier for the rate limits
+pub const SENDER_ESCROW_LOG: Map<Addr, Vec<Timestamp>> = Map::new("sender_escrow_log");
+pub const INBOUND_ESCROW_LOG: Map<String, Vec<Timestamp>> = Map::new("inbound_escrow_log");
+pub const CONFIG: Item<Config> = Item::new("config");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TipStatus {
+    Sent,
+    Escrowed,
+    Claimed,
+    Refunded,
+    // micro tip waiting in its recipient's bucket; the fee is charged on the
+    // bucket total, so the record carries none until it is settled
+    Batched,
+    // micro tip paid out with its bucket, carrying its share of the bucket fee
+    Settled,
+}
+
+// a single tip as recorded in the history ledger
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Tip {
+    pub id: u64,
+    pub sender: Addr,
+    pub recipient: String,
+    pub gross: Uint128,
+    pub fee: Uint128,
+    pub net: Uint128,
+    pub denom: String,
+    pub time: Timestamp,
+    pub memo: Option<String>,
+    pub status: TipStatus,
+}
+
+pub struct TipIndexes<'a> {
+    pub sender: MultiIndex<'a, Addr, Tip, u64>,
+    pub recipient: MultiIndex<'a, String, Tip, u64>,
+    // 1 while an escrow or micro-tip bucket still refers to the record, 0
+    // once it may be pruned
+    pub unsettled: MultiIndex<'a, u8, Tip, u64>,
+}
+
+impl IndexList<Tip> for TipIndexes<'_> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Tip>> + '_> {
+        let v: Vec<&dyn Index<Tip>> = vec![&self.sender, &self.recipient, &self.unsettled];
+        Box::new(v.into_iter())
+    }
+}
+
+// append-only tip history, indexed by sender and recipient identifier
+pub fn tips<'a>() -> IndexedMap<u64, Tip, TipIndexes<'a>> {
+    let indexes = TipIndexes {
+        sender: MultiIndex::new(|_pk, t: &Tip| t.sender.clone(), "tips", "tips__sender"),
+        recipient: MultiIndex::new(|_pk, t: &Tip| t.recipient.clone(), "tips", "tips__recipient"),
+        unsettled: MultiIndex::new(
+            |_pk, t: &Tip| u8::from(matches!(t.status, TipStatus::Escrowed | TipStatus::Batched)),
+            "tips",
+            "tips__unsettled",
+        ),
+    };
+    IndexedMap::new("tips", indexes)
+}
+
+// last assigned tip id
+pub const TIP_COUNT: Item<u64> = Item::new("tip_count");
+
+// number of tip records currently kept in the ledger
+pub const TIP_RECORDS: Item<u64> = Item::new("tip_records");
+
+// running tip volume for a denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, JsonSchema)]
+pub struct DenomStats {
+    pub volume: Uint128,
+    pub tips: u64,
+}
+
+pub const DENOM_STATS: Map<String, DenomStats> = Map::new("denom_stats");
+
+// running total for one (identifier or sender, denom) pair
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, JsonSchema)]
+pub struct TipTotal {
+    pub denom: String,
+    pub amount: Uint128,
+    pub count: u64,
+}
+
+pub struct TotalIndexes<'a> {
+    // (denom, amount) so leaderboards can be read in amount order
+    pub amount: MultiIndex<'a, (String, u128), TipTotal, (String, String)>,
+}
+
+impl IndexList<TipTotal> for TotalIndexes<'_> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<TipTotal>> + '_> {
+        let v: Vec<&dyn Index<TipTotal>> = vec![&self.amount];
+        Box::new(v.into_iter())
+    }
+}
+
+fn total_amount_index(_pk: &[u8], total: &TipTotal) -> (String, u128) {
+    (total.denom.clone(), total.amount.u128())
+}
+
+// net amounts received per (identifier, denom)
+pub fn received_totals<'a>() -> IndexedMap<(String, String), TipTotal, TotalIndexes<'a>> {
+    let indexes = TotalIndexes {
+        amount: MultiIndex::new(total_amount_index, "received_totals", "received_totals__amount"),
+    };
+    IndexedMap::new("received_totals", indexes)
+}
+
+// gross amounts sent per (sender address, denom)
+pub fn sent_totals<'a>() -> IndexedMap<(String, String), TipTotal, TotalIndexes<'a>> {
+    let indexes = TotalIndexes {
+        amount: MultiIndex::new(total_amount_index, "sent_totals", "sent_totals__amount"),
+    };
+    IndexedMap::new("sent_totals", indexes)
+}
+
+// next expected relayed-claim nonce per signing account
+pub const RELAY_NONCES: Map<Addr, u64> = Map::new("relay_nonces");
+
+// height at which each (escrow id, claim_reveal_hash) was committed
+pub const CLAIM_COMMITS: Map<(u64, String), u64> = Map::new("claim_commits");
+
+// one wallet's cut of the platform fee, in basis points
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct FeeShare {
+    pub address: Addr,
+    pub bps: u16,
+}
+
+// fees collected per denom and not yet withdrawn
+pub const FEE_POOL: Map<String, Uint128> = Map::new("fee_pool");
+
+// who referred a registered identifier, keyed by the referred identifier
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Referral {
+    pub referrer: String,
+    pub since: Timestamp,
+}
+
+pub const REFERRALS: Map<String, Referral> = Map::new("referrals");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, JsonSchema)]
+pub struct ReferralEarnings {
+    // everything ever credited to the referrer
+    pub earned: Uint128,
+    // credited but not yet claimed
+    pub unpaid: Uint128,
+}
+
+// referral rewards per (referrer identifier, denom)
+pub const REFERRAL_EARNINGS: Map<(String, String), ReferralEarnings> = Map::new("referral_earnings");
+
+// public creator profile for a registered identifier, rendered on tip pages
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, JsonSchema)]
+pub struct Profile {
+    pub display_name: Option<String>,
+    // https:// or ipfs:// image
+    pub avatar_uri: Option<String>,
+    pub bio: Option<String>,
+    #[serde(default)]
+    pub links: Vec<String>,
+    // denom the creator would rather be tipped in
+    pub payout_denom: Option<String>,
+}
+
+pub const PROFILES: Map<String, Profile> = Map::new("profiles");
+
+// How tips to a registered identifier reach its owner. Swapping into another
+// denom would need a DEX route and is not offered.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutPolicy {
+    // send to the registered address
+    #[default]
+    Direct,
+    // send everything to another address
+    Forward { address: Addr },
+    // send `bps` of every tip to a savings address and the rest to the owner
+    Split { savings: Addr, bps: u16 },
+    // keep tips in the contract as an internal balance of the owner
+    Hold,
+}
+
+impl PayoutPolicy {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PayoutPolicy::Direct => "direct",
+            PayoutPolicy::Forward { .. } => "forward",
+            PayoutPolicy::Split { .. } => "split",
+            PayoutPolicy::Hold => "hold",
+        }
+    }
+
+    // addresses other than the owner's that payouts are sent to
+    pub fn destinations(&self) -> Vec<&Addr> {
+        match self {
+            PayoutPolicy::Forward { address } => vec![address],
+            PayoutPolicy::Split { savings, .. } => vec![savings],
+            PayoutPolicy::Direct | PayoutPolicy::Hold => vec![],
+        }
+    }
+}
+
+pub const PAYOUT_POLICIES: Map<String, PayoutPolicy> = Map::new("payout_policies");
+
+// internal balances held by the contract per (owner, denom)
+pub const BALANCES: Map<(Addr, String), Uint128> = Map::new("balances");
+
+// micro tips waiting for Settle, per (identifier, denom)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, JsonSchema)]
+pub struct MicroTipBucket {
+    pub gross: Uint128,
+    pub tips: u32,
+    // records of the batched tips, given their share of the fee on Settle
+    #[serde(default)]
+    pub tip_ids: Vec<u64>,
+}
+
+pub const MICRO_TIP_BUCKETS: Map<(String, String), MicroTipBucket> = Map::new("micro_tip_buckets");
+
+// what a spender may still tip out of an owner's internal balance in one denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Allowance {
+    pub remaining: Uint128,
+    // largest single tip the spender may send
+    pub per_tip_cap: Option<Uint128>,
+    pub expires: Option<Timestamp>,
+}
+
+// allowances per (owner, spender, denom)
+pub const ALLOWANCES: Map<(Addr, Addr, String), Allowance> = Map::new("allowances");
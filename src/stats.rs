@@ -0,0 +1,39 @@
+use cosmwasm_std::{Coin, StdResult, Storage, Uint128};
+
+use crate::state::{received_totals, sent_totals, TipTotal, DENOM_STATS};
+
+// count a new tip towards the denom volume and the sender's running total
+pub fn record_sent(storage: &mut dyn Storage, sender: &str, gross: &Coin) -> StdResult<()> {
+    let mut stats = DENOM_STATS.may_load(storage, gross.denom.clone())?.unwrap_or_default();
+    stats.volume += gross.amount;
+    stats.tips += 1;
+    DENOM_STATS.save(storage, gross.denom.clone(), &stats)?;
+
+    add_to_total(storage, false, sender, &gross.denom, gross.amount, 1)
+}
+
+// credit what actually reaches the recipient; `tips` is how many tips this
+// completes, so a tip claimed in parts counts once
+pub fn record_received(storage: &mut dyn Storage, identifier: &str, net: &Coin, tips: u64) -> StdResult<()> {
+    add_to_total(storage, true, identifier, &net.denom, net.amount, tips)
+}
+
+fn add_to_total(
+    storage: &mut dyn Storage,
+    received: bool,
+    key: &str,
+    denom: &str,
+    amount: Uint128,
+    tips: u64,
+) -> StdResult<()> {
+    let map = if received { received_totals() } else { sent_totals() };
+    let pk = (key.to_string(), denom.to_string());
+    let mut total = map.may_load(storage, pk.clone())?.unwrap_or(TipTotal {
+        denom: denom.to_string(),
+        ..TipTotal::default()
+    });
+    total.amount += amount;
+    total.count += tips;
+    // saving through the indexed map moves the entry in the amount index
+    map.save(storage, pk, &total)
+}